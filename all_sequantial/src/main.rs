@@ -65,7 +65,7 @@ fn main() {
     let day_17 = Instant::now();
     println!("Day 17");
     let input_str = read_file(String::from("../all_inputs/aoc_17_input.txt"));
-    let (part_1, part_2) = aoc_17_comp(&input_str);
+    let (part_1, part_2) = aoc_17_comp(&input_str).unwrap();
     println!("Part 1: {:?}", part_1);
     println!("Part 2: {:?}", part_2);
     println!("Day 17 took {:?}\n\n", day_17.elapsed());
@@ -1,88 +1,454 @@
 #![feature(test)]
 use std::collections::{HashMap, HashSet};
+use std::str::FromStr;
 
 pub fn aoc_12_comp(input: &str) -> (usize, usize) {
-    let input = String::from(input);
-    let paths_1 = find_paths(&input, recursion_criteria_part_1);
-    let paths_2 = find_paths(&input, recursion_criteria_part_2);
-    return (paths_1.len(), paths_2.len());
+    let caves = input.trim_end().parse::<CaveGraph>().unwrap();
+    let paths_1 = caves.count_paths(Visits::SmallOnce);
+    let paths_2 = caves.count_paths(Visits::OneSmallTwice);
+    return (paths_1 as usize, paths_2 as usize);
 }
 
-pub fn find_paths(
-    input: &String,
-    criterion: fn(&String, &Vec<String>) -> bool,
-) -> HashSet<Vec<String>> {
-    let connections = create_connections(input);
-    let paths = search_paths_recursive(vec![String::from("start")], &connections, criterion);
+/// How often a path may enter the small caves.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Visits {
+    SmallOnce,
+    /// A single small cave other than `start` and `end` may be visited twice
+    OneSmallTwice,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum InvalidCaves {
+    /// A line that is not of the form `cave-cave`
+    NoConnection {
+        line: String,
+    },
+    MissingCave {
+        name: &'static str,
+    },
+    /// Paths could bounce between two big caves forever
+    BigCavesConnected {
+        lhs: String,
+        rhs: String,
+    },
+    TooManySmallCaves {
+        count: usize,
+    },
+}
+
+/// Caves interned to integer IDs, small caves additionally get a bit in a visited mask.
+#[derive(Debug)]
+pub struct CaveGraph {
+    names: Vec<String>,
+    neighbours: Vec<Vec<usize>>,
+    /// Bit of each small cave, 0 for big caves
+    masks: Vec<u64>,
+    start: usize,
+    end: usize,
+}
+
+impl FromStr for CaveGraph {
+    type Err = InvalidCaves;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut ids = HashMap::<String, usize>::new();
+        let mut names = vec![];
+        let mut neighbours = vec![];
+        let mut masks = vec![];
+        let mut intern = |name: &str| -> Result<usize, InvalidCaves> {
+            if let Some(id) = ids.get(name) {
+                return Ok(*id);
+            }
+            let mask = if is_uppercase(name) {
+                0
+            } else {
+                let small_caves = masks.iter().filter(|mask| **mask != 0).count();
+                if small_caves == u64::BITS as usize {
+                    return Err(InvalidCaves::TooManySmallCaves {
+                        count: small_caves + 1,
+                    });
+                }
+                1 << small_caves
+            };
+            ids.insert(String::from(name), names.len());
+            names.push(String::from(name));
+            neighbours.push(vec![]);
+            masks.push(mask);
+            return Ok(names.len() - 1);
+        };
+
+        let mut connections = vec![];
+        for line in input.lines() {
+            let (cave_1, cave_2) =
+                line.split_once("-")
+                    .ok_or_else(|| InvalidCaves::NoConnection {
+                        line: String::from(line),
+                    })?;
+            connections.push((intern(cave_1)?, intern(cave_2)?));
+        }
+        let start = *ids
+            .get("start")
+            .ok_or(InvalidCaves::MissingCave { name: "start" })?;
+        let end = *ids
+            .get("end")
+            .ok_or(InvalidCaves::MissingCave { name: "end" })?;
+
+        for (cave_1, cave_2) in connections {
+            if masks[cave_1] == 0 && masks[cave_2] == 0 {
+                return Err(InvalidCaves::BigCavesConnected {
+                    lhs: names[cave_1].clone(),
+                    rhs: names[cave_2].clone(),
+                });
+            }
+            if !neighbours[cave_1].contains(&cave_2) {
+                neighbours[cave_1].push(cave_2);
+                neighbours[cave_2].push(cave_1);
+            }
+        }
+        return Ok(CaveGraph {
+            names,
+            neighbours,
+            masks,
+            start,
+            end,
+        });
+    }
+}
+
+impl CaveGraph {
+    /// The graph on the small caves, `start` and `end` included. A step between two small
+    /// caves is either a direct connection or a detour through a single big cave, big caves
+    /// are never connected to each other so no path visits two in a row.
+    fn collapse(self: &Self) -> SmallCaves {
+        let small = (0..self.names.len())
+            .filter(|cave| self.masks[*cave] != 0)
+            .collect::<Vec<usize>>();
+        let mut index = vec![usize::MAX; self.names.len()];
+        for (i, &cave) in small.iter().enumerate() {
+            index[cave] = i;
+        }
+
+        let mut steps = vec![];
+        let mut adjacent = vec![];
+        for &cave in &small {
+            let mut ways = HashMap::<usize, u64>::new();
+            for &next in &self.neighbours[cave] {
+                if self.masks[next] != 0 {
+                    *ways.entry(index[next]).or_insert(0) += 1;
+                    continue;
+                }
+                // Through the big cave, possibly back to where the path came from
+                for &after in &self.neighbours[next] {
+                    *ways.entry(index[after]).or_insert(0) += 1;
+                }
+            }
+            ways.remove(&index[self.start]);
+            let mut ways = ways.into_iter().collect::<Vec<(usize, u64)>>();
+            ways.sort();
+            adjacent.push(ways.iter().fold(0, |mask, (next, _)| mask | 1 << next));
+            steps.push(ways);
+        }
+        return SmallCaves {
+            steps,
+            adjacent,
+            start: index[self.start],
+            end: index[self.end],
+        };
+    }
+
+    /// Number of paths from `start` to `end`. Big caves are collapsed into weighted edges
+    /// first, the count is memoised on (small cave, small caves the path can still reach,
+    /// visited ones among those, whether a small cave may still be visited twice). This is
+    /// still exponential in the number of small caves that stay reachable from each other:
+    /// densely connected systems beyond about 20 small caves take seconds. Systems made of
+    /// loosely joined parts count in milliseconds up to the 64 small cave limit, see
+    /// `bench_day_12_large`.
+    pub fn count_paths(self: &Self, visits: Visits) -> u64 {
+        let caves = self.collapse();
+        let mut memo = HashMap::new();
+        return caves.count_from(
+            caves.start,
+            1 << caves.start,
+            visits == Visits::OneSmallTwice,
+            &mut memo,
+        );
+    }
+
+    /// Every path from `start` to `end`, walked on a single stack of cave IDs.
+    pub fn paths(self: &Self, visits: Visits) -> HashSet<Vec<String>> {
+        let mut paths = HashSet::new();
+        let mut path = vec![self.start];
+        self.walk(
+            &mut path,
+            self.masks[self.start],
+            visits == Visits::OneSmallTwice,
+            &mut paths,
+        );
+        return paths;
+    }
+
+    fn walk(
+        self: &Self,
+        path: &mut Vec<usize>,
+        visited: u64,
+        may_revisit: bool,
+        paths: &mut HashSet<Vec<String>>,
+    ) {
+        let cave = *path.last().unwrap();
+        if cave == self.end {
+            paths.insert(path.iter().map(|id| self.names[*id].clone()).collect());
+            return;
+        }
+        for &next in &self.neighbours[cave] {
+            if next == self.start {
+                continue;
+            }
+            let mask = self.masks[next];
+            let state = if visited & mask == 0 {
+                (visited | mask, may_revisit)
+            } else if may_revisit {
+                (visited, false)
+            } else {
+                continue;
+            };
+            path.push(next);
+            self.walk(path, state.0, state.1, paths);
+            path.pop();
+        }
+    }
+}
 
-    return paths;
+/// Indices of the set bits of `mask`, lowest first.
+fn bits(mut mask: u64) -> impl Iterator<Item = usize> {
+    return std::iter::from_fn(move || {
+        if mask == 0 {
+            return None;
+        }
+        let bit = mask.trailing_zeros() as usize;
+        mask &= mask - 1;
+        return Some(bit);
+    });
 }
 
-fn create_connections(input: &String) -> HashMap<String, HashSet<String>> {
-    let mut connections = HashMap::<String, HashSet<String>>::new();
-    for line in input.lines() {
-        let (cave_1, cave_2) = line.split_once("-").unwrap();
-        connections
-            .entry(String::from(cave_1))
-            .or_insert(HashSet::new())
-            .insert(String::from(cave_2));
-        connections
-            .entry(String::from(cave_2))
-            .or_insert(HashSet::new())
-            .insert(String::from(cave_1));
+/// Small caves after collapsing the big ones, bit `i` of a mask stands for cave `i`.
+struct SmallCaves {
+    /// Caves reachable in one step and the number of ways to get there
+    steps: Vec<Vec<(usize, u64)>>,
+    adjacent: Vec<u64>,
+    start: usize,
+    end: usize,
+}
+
+impl SmallCaves {
+    /// Caves reachable from `cave` over the caves in `open`.
+    fn reachable(self: &Self, cave: usize, open: u64) -> u64 {
+        let mut seen = 0;
+        let mut frontier = self.adjacent[cave] & open;
+        while frontier != 0 {
+            seen |= frontier;
+            let next = bits(frontier).fold(0, |mask, cave| mask | self.adjacent[cave]);
+            frontier = next & open & !seen;
+        }
+        return seen;
+    }
+
+    fn count_from(
+        self: &Self,
+        cave: usize,
+        visited: u64,
+        may_revisit: bool,
+        memo: &mut HashMap<(usize, u64, u64, bool), u64>,
+    ) -> u64 {
+        if cave == self.end {
+            return 1;
+        }
+        // The count only depends on the caves the path can still reach and which of them
+        // are visited. Caves behind the path are dropped, so distant parts of the system
+        // stop multiplying the states.
+        let mut reachable = self.reachable(cave, !visited);
+        if may_revisit {
+            // One visited cave next to the open area may be entered again to get beyond it
+            let around = reachable | 1 << cave;
+            let mut again = bits(around).fold(0, |mask, next| mask | self.adjacent[next])
+                & visited
+                & !(1 << self.start);
+            reachable |= again;
+            while again != 0 {
+                let next = again.trailing_zeros() as usize;
+                reachable |= self.reachable(next, !visited);
+                again &= again - 1;
+            }
+        }
+        if reachable & 1 << self.end == 0 {
+            return 0;
+        }
+        let key = (cave, reachable, visited & reachable, may_revisit);
+        if let Some(count) = memo.get(&key) {
+            return *count;
+        }
+        let mut count: u64 = 0;
+        for &(next, ways) in &self.steps[cave] {
+            let bit = 1 << next;
+            let paths = if visited & bit == 0 {
+                self.count_from(next, visited | bit, may_revisit, memo)
+            } else if may_revisit {
+                self.count_from(next, visited, false, memo)
+            } else {
+                continue;
+            };
+            count = ways
+                .checked_mul(paths)
+                .and_then(|paths| count.checked_add(paths))
+                .expect("number of paths does not fit in a u64");
+        }
+        memo.insert(key, count);
+        return count;
     }
-    return connections;
 }
 
-fn is_lowercase(text: &String) -> bool {
-    return text.chars().all(|c| char::is_lowercase(c));
+pub fn find_paths(input: &String, visits: Visits) -> HashSet<Vec<String>> {
+    return input.parse::<CaveGraph>().unwrap().paths(visits);
 }
 
-fn is_uppercase(text: &String) -> bool {
+pub fn count_paths(input: &String, visits: Visits) -> u64 {
+    return input.parse::<CaveGraph>().unwrap().count_paths(visits);
+}
+
+fn is_uppercase(text: &str) -> bool {
     return text.chars().all(|c| char::is_uppercase(c));
 }
 
-fn search_paths_recursive(
-    path: Vec<String>,
-    connections: &HashMap<String, HashSet<String>>,
-    criterion: fn(&String, &Vec<String>) -> bool,
-) -> HashSet<Vec<String>> {
-    let options = connections.get(path.last().unwrap()).unwrap();
-
-    let mut paths = HashSet::new();
-    for next_cave in options {
-        let mut current_path = path.clone();
-        if next_cave == "end" {
-            current_path.push(next_cave.clone());
-            paths.insert(current_path);
-        } else if next_cave == "start" {
-            continue;
-        } else if criterion(next_cave, &current_path) {
-            current_path.push(next_cave.clone());
-            paths.extend(search_paths_recursive(current_path, connections, criterion));
+/// Xorshift generator, keeps the generated cave systems reproducible from a seed.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        return XorShift((seed ^ 0x9E37_79B9_7F4A_7C15).max(1));
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        return self.0;
+    }
+}
+
+/// Letters for the `index`-th cave, `a`, ..., `z`, `ba`, ... in lower or upper case.
+fn cave_name(mut index: usize, big: bool) -> String {
+    let first = if big { b'A' } else { b'a' };
+    let mut letters = vec![];
+    loop {
+        letters.push((first + (index % 26) as u8) as char);
+        index /= 26;
+        if index == 0 {
+            break;
         }
     }
+    return letters.iter().rev().collect();
+}
+
+/// A random cave system in the puzzle format. `start`, `end` and every small cave get
+/// connected to up to `degree` random caves, big caves are never connected to each other.
+pub fn generate_caves(small_caves: usize, big_caves: usize, degree: usize, seed: u64) -> String {
+    if small_caves + big_caves == 0 {
+        return String::from("start-end");
+    }
+    let mut rng = XorShift::new(seed);
+    let mut names = vec![String::from("start"), String::from("end")];
+    names.extend((0..small_caves).map(|index| cave_name(index, false)));
+    names.extend((0..big_caves).map(|index| cave_name(index, true)));
+    let first_big = names.len() - big_caves;
 
-    return paths;
+    let mut connections = HashSet::new();
+    // `start` and `end` only ever connect into the rest of the system
+    let inner = (2..names.len()).collect::<Vec<usize>>();
+    for cave in 0..first_big {
+        connect_randomly(&mut rng, cave, &inner, degree, &mut connections);
+    }
+    return write_connections(&names, connections);
 }
 
-fn recursion_criteria_part_1(next_cave: &String, current_path: &Vec<String>) -> bool {
-    return is_uppercase(next_cave) || !current_path.contains(next_cave);
+/// Connects `cave` to up to `degree` random caves out of `others`.
+fn connect_randomly(
+    rng: &mut XorShift,
+    cave: usize,
+    others: &[usize],
+    degree: usize,
+    connections: &mut HashSet<(usize, usize)>,
+) {
+    for _ in 0..degree {
+        let other = others[rng.next() as usize % others.len()];
+        if other != cave {
+            connections.insert((cave.min(other), cave.max(other)));
+        }
+    }
+}
+
+fn write_connections(names: &[String], connections: HashSet<(usize, usize)>) -> String {
+    let mut connections = connections.into_iter().collect::<Vec<_>>();
+    connections.sort();
+    return connections
+        .iter()
+        .map(|(cave_1, cave_2)| format!("{}-{}", names[*cave_1], names[*cave_2]))
+        .collect::<Vec<_>>()
+        .join("\n");
 }
 
-fn recursion_criteria_part_2(next_cave: &String, current_path: &Vec<String>) -> bool {
-    return is_uppercase(next_cave)
-        || !current_path.contains(next_cave)
-        || (is_lowercase(next_cave)
-            && current_path
-                .iter()
-                .filter(|x| is_lowercase(x))
-                .fold(HashMap::<&String, u128>::new(), |mut m, x| {
-                    *m.entry(x).or_insert(0) += 1;
-                    m
-                })
-                .iter()
-                .all(|(_cave, count)| count < &2));
+/// A large cave system made of `rooms` random systems like `generate_caves` in a row. Each
+/// room is entered and left through a small cave it shares with its neighbours, the first
+/// room is entered from `start` and the last one leads to `end`.
+pub fn generate_cave_rooms(
+    rooms: usize,
+    small_caves: usize,
+    big_caves: usize,
+    degree: usize,
+    seed: u64,
+) -> String {
+    let mut rng = XorShift::new(seed);
+    let mut names = vec![String::from("start")];
+    // Doors between the rooms, the first is `start` and the last `end`
+    let mut doors = vec![0];
+    let (mut small, mut big) = (0, 0);
+    let mut rooms_caves = vec![];
+    for room in 0..rooms {
+        let mut caves = vec![];
+        for _ in 0..small_caves {
+            caves.push(names.len());
+            names.push(cave_name(small, false));
+            small += 1;
+        }
+        let first_big = caves.len();
+        for _ in 0..big_caves {
+            caves.push(names.len());
+            names.push(cave_name(big, true));
+            big += 1;
+        }
+        doors.push(names.len());
+        match room + 1 == rooms {
+            true => names.push(String::from("end")),
+            false => {
+                names.push(cave_name(small, false));
+                small += 1;
+            }
+        }
+        rooms_caves.push((caves, first_big));
+    }
+
+    let mut connections = HashSet::new();
+    for (room, (caves, first_big)) in rooms_caves.iter().enumerate() {
+        if caves.is_empty() {
+            connections.insert((doors[room], doors[room + 1]));
+            continue;
+        }
+        for &cave in [doors[room], doors[room + 1]]
+            .iter()
+            .chain(&caves[..*first_big])
+        {
+            connect_randomly(&mut rng, cave, caves, degree, &mut connections);
+        }
+    }
+    return write_connections(&names, connections);
 }
 
 #[cfg(test)]
@@ -178,7 +544,7 @@ b-end
             ],
         ]);
 
-        let paths = find_paths(&input, recursion_criteria_part_1);
+        let paths = find_paths(&input, Visits::SmallOnce);
 
         assert_eq!(expected, paths)
     }
@@ -195,7 +561,7 @@ pj-RW\nzg-RW\nstart-pj\nhe-WI\nzg-he\npj-fs\nstart-RW",
         226
     )]
     fn test_path_count(#[case] input: String, #[case] count: usize) {
-        assert_eq!(find_paths(&input, recursion_criteria_part_1).len(), count)
+        assert_eq!(find_paths(&input, Visits::SmallOnce).len(), count)
     }
 
     #[rstest]
@@ -210,7 +576,109 @@ pj-RW\nzg-RW\nstart-pj\nhe-WI\nzg-he\npj-fs\nstart-RW",
         3509
     )]
     fn test_path_count_v2(#[case] input: String, #[case] count: usize) {
-        assert_eq!(find_paths(&input, recursion_criteria_part_2).len(), count)
+        assert_eq!(find_paths(&input, Visits::OneSmallTwice).len(), count)
+    }
+
+    #[rstest]
+    #[case("start-A\nstart-b\nA-c\nA-b\nb-d\nA-end\nb-end", 10, 36)]
+    #[case(
+        "dc-end\nHN-start\nstart-kj\ndc-start\ndc-HN\nLN-dc\nHN-end\nkj-sa\nkj-HN\nkj-dc",
+        19,
+        103
+    )]
+    #[case(
+        "fs-end\nhe-DX\nfs-he\nstart-DX\npj-DX\nend-zg\nzg-sl\nzg-pj\npj-he\nRW-he\nfs-DX
+pj-RW\nzg-RW\nstart-pj\nhe-WI\nzg-he\npj-fs\nstart-RW",
+        226,
+        3509
+    )]
+    fn test_count_paths(#[case] input: String, #[case] once: u64, #[case] twice: u64) {
+        assert_eq!(count_paths(&input, Visits::SmallOnce), once);
+        assert_eq!(count_paths(&input, Visits::OneSmallTwice), twice);
+    }
+
+    #[rstest]
+    #[case(5, 3, 3, 1)]
+    #[case(8, 4, 3, 2)]
+    #[case(10, 3, 2, 3)]
+    fn test_count_paths_generated(
+        #[case] small_caves: usize,
+        #[case] big_caves: usize,
+        #[case] degree: usize,
+        #[case] seed: u64,
+    ) {
+        let input = generate_caves(small_caves, big_caves, degree, seed);
+        for visits in [Visits::SmallOnce, Visits::OneSmallTwice] {
+            assert_eq!(
+                count_paths(&input, visits),
+                find_paths(&input, visits).len() as u64
+            );
+        }
+    }
+
+    #[rstest]
+    #[case(1, 4, 1, 2, 5)]
+    #[case(3, 3, 1, 2, 8)]
+    #[case(2, 0, 0, 2, 1)]
+    fn test_count_paths_rooms(
+        #[case] rooms: usize,
+        #[case] small_caves: usize,
+        #[case] big_caves: usize,
+        #[case] degree: usize,
+        #[case] seed: u64,
+    ) {
+        let input = generate_cave_rooms(rooms, small_caves, big_caves, degree, seed);
+        for visits in [Visits::SmallOnce, Visits::OneSmallTwice] {
+            assert_eq!(
+                count_paths(&input, visits),
+                find_paths(&input, visits).len() as u64
+            );
+        }
+    }
+
+    #[rstest]
+    fn test_count_paths_large() {
+        let caves = generate_cave_rooms(12, 4, 1, 3, 12)
+            .parse::<CaveGraph>()
+            .unwrap();
+        assert_eq!(caves.masks.iter().filter(|mask| **mask != 0).count(), 61);
+        let once = caves.count_paths(Visits::SmallOnce);
+        assert!(once > 1_000_000);
+        assert!(caves.count_paths(Visits::OneSmallTwice) > once);
+    }
+
+    #[rstest]
+    fn test_generate_caves() {
+        let input = generate_caves(15, 5, 2, 12);
+        assert_eq!(input, generate_caves(15, 5, 2, 12));
+        assert_eq!(generate_caves(0, 0, 2, 12), "start-end");
+        let caves = input.parse::<CaveGraph>().unwrap();
+        assert_eq!(caves.names.len(), 22);
+        assert!(caves.count_paths(Visits::OneSmallTwice) > caves.count_paths(Visits::SmallOnce));
+    }
+
+    #[rstest]
+    #[case("start-A\nA-end\nA", InvalidCaves::NoConnection { line: String::from("A") })]
+    #[case("start-a\na-b", InvalidCaves::MissingCave { name: "end" })]
+    #[case("a-end\na-b", InvalidCaves::MissingCave { name: "start" })]
+    #[case(
+        "start-A\nA-B\nB-end",
+        InvalidCaves::BigCavesConnected { lhs: String::from("A"), rhs: String::from("B") }
+    )]
+    fn test_invalid_caves(#[case] input: &str, #[case] expected: InvalidCaves) {
+        assert_eq!(input.parse::<CaveGraph>().unwrap_err(), expected);
+    }
+
+    #[rstest]
+    fn test_too_many_small_caves() {
+        let input = (0..65)
+            .map(|index| format!("start-{}", cave_name(index, false)))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            input.parse::<CaveGraph>().unwrap_err(),
+            InvalidCaves::TooManySmallCaves { count: 65 }
+        );
     }
 
     #[test]
@@ -236,8 +704,8 @@ TG-uq
 end-SM
 SM-uq",
         );
-        assert_eq!(3708, find_paths(&input, recursion_criteria_part_1).len());
-        assert_eq!(93858, find_paths(&input, recursion_criteria_part_2).len());
+        assert_eq!(3708, find_paths(&input, Visits::SmallOnce).len());
+        assert_eq!(93858, find_paths(&input, Visits::OneSmallTwice).len());
     }
 
     #[bench]
@@ -263,7 +731,7 @@ TG-uq
 end-SM
 SM-uq",
         );
-        b.iter(|| find_paths(&input, recursion_criteria_part_1))
+        b.iter(|| find_paths(&input, Visits::SmallOnce))
     }
 
     #[bench]
@@ -289,6 +757,20 @@ TG-uq
 end-SM
 SM-uq",
         );
-        b.iter(|| find_paths(&input, recursion_criteria_part_2))
+        b.iter(|| find_paths(&input, Visits::OneSmallTwice))
+    }
+
+    #[bench]
+    fn bench_day_12_large(b: &mut Bencher) {
+        let caves = generate_cave_rooms(12, 4, 1, 3, 12)
+            .parse::<CaveGraph>()
+            .unwrap();
+        b.iter(|| caves.count_paths(Visits::OneSmallTwice))
+    }
+
+    #[bench]
+    fn bench_day_12_generated(b: &mut Bencher) {
+        let caves = generate_caves(15, 5, 2, 12).parse::<CaveGraph>().unwrap();
+        b.iter(|| caves.count_paths(Visits::OneSmallTwice))
     }
 }
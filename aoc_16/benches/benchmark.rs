@@ -19,6 +19,9 @@ fn speed_test_day_16(c: &mut Criterion) {
     c.bench_function("day 16 parse packet", |b| {
         b.iter(|| decode_str(black_box(&message_str)))
     });
+    c.bench_function("day 16 stream packets", |b| {
+        b.iter(|| decode_reader(black_box(message_str.as_bytes())))
+    });
 
     let packet = decode_str(&message_str);
     c.bench_function("day 16 count version", |b| {
@@ -1,6 +1,6 @@
 #![feature(destructuring_assignment)]
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, Bytes, Read};
 
 use itertools::Itertools;
 
@@ -222,6 +222,263 @@ fn decode_bits(message_bits: &mut impl Iterator<Item = char>) -> Packet {
     }
 }
 
+#[derive(PartialEq, Debug)]
+pub enum PacketEvent {
+    EnterOperator { version: u8, type_id: u8 },
+    Literal { version: u8, value: u64 },
+    ExitOperator { type_id: u8 },
+}
+
+/// Reads the hex encoded transmission one bit at a time, skipping whitespace.
+/// Bits can be pushed back so the top level can look ahead for padding.
+struct BitReader<R: Read> {
+    bytes: Bytes<BufReader<R>>,
+    nibble: u8,
+    nibble_bits: u8,
+    pushed_back_zeros: usize,
+    pushed_back_one: bool,
+    position: usize,
+}
+
+impl<R: Read> BitReader<R> {
+    fn new(reader: R) -> BitReader<R> {
+        return BitReader {
+            bytes: BufReader::new(reader).bytes(),
+            nibble: 0,
+            nibble_bits: 0,
+            pushed_back_zeros: 0,
+            pushed_back_one: false,
+            position: 0,
+        };
+    }
+
+    fn next_nibble(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            let byte = match self.bytes.next() {
+                Some(byte) => byte?,
+                None => return Ok(None),
+            };
+            if byte.is_ascii_whitespace() {
+                continue;
+            }
+            return match (byte as char).to_digit(16) {
+                Some(nibble) => Ok(Some(nibble as u8)),
+                None => Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Unexpected input value: {:?}", byte as char),
+                )),
+            };
+        }
+    }
+
+    fn next_bit(&mut self) -> io::Result<Option<bool>> {
+        if self.pushed_back_zeros > 0 {
+            self.pushed_back_zeros -= 1;
+            self.position += 1;
+            return Ok(Some(false));
+        }
+        if self.pushed_back_one {
+            self.pushed_back_one = false;
+            self.position += 1;
+            return Ok(Some(true));
+        }
+        if self.nibble_bits == 0 {
+            match self.next_nibble()? {
+                Some(nibble) => {
+                    self.nibble = nibble;
+                    self.nibble_bits = 4;
+                }
+                None => return Ok(None),
+            }
+        }
+        self.nibble_bits -= 1;
+        self.position += 1;
+        return Ok(Some((self.nibble >> self.nibble_bits) & 1 == 1));
+    }
+
+    fn read_bits(&mut self, n: u8) -> io::Result<u64> {
+        let mut value = 0;
+        for _ in 0..n {
+            match self.next_bit()? {
+                Some(bit) => value = (value << 1) | bit as u64,
+                None => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        "Transmission ended inside a packet",
+                    ))
+                }
+            }
+        }
+        return Ok(value);
+    }
+
+    /// Returns false if only zero padding is left in the transmission.
+    /// Otherwise the consumed zeros are pushed back so the next packet is read intact.
+    fn has_packet(&mut self) -> io::Result<bool> {
+        let mut zeros = 0;
+        loop {
+            match self.next_bit()? {
+                Some(false) => zeros += 1,
+                Some(true) => {
+                    self.pushed_back_zeros = zeros;
+                    self.pushed_back_one = true;
+                    self.position -= zeros + 1;
+                    return Ok(true);
+                }
+                None => return Ok(false),
+            }
+        }
+    }
+}
+
+enum PacketLimit {
+    EndPosition(usize),
+    SubPackets(u64),
+}
+
+struct OpenOperator {
+    type_id: u8,
+    limit: PacketLimit,
+}
+
+/// Decodes a transmission from any reader, emitting an event for every packet as soon as its
+/// header (and for literals, its value) is read. Multiple top level packets are decoded back
+/// to back until only zero padding remains.
+pub struct PacketStream<R: Read> {
+    bits: BitReader<R>,
+    open: Vec<OpenOperator>,
+    finished: bool,
+}
+
+impl<R: Read> PacketStream<R> {
+    pub fn new(reader: R) -> PacketStream<R> {
+        return PacketStream {
+            bits: BitReader::new(reader),
+            open: vec![],
+            finished: false,
+        };
+    }
+
+    fn next_event(&mut self) -> io::Result<Option<PacketEvent>> {
+        match self.open.last() {
+            Some(operator) => {
+                let closed = match operator.limit {
+                    PacketLimit::EndPosition(end) => {
+                        if self.bits.position > end {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                "Sub packets exceed the operator length",
+                            ));
+                        }
+                        self.bits.position == end
+                    }
+                    PacketLimit::SubPackets(remaining) => remaining == 0,
+                };
+                if closed {
+                    let type_id = self.open.pop().unwrap().type_id;
+                    return Ok(Some(PacketEvent::ExitOperator { type_id }));
+                }
+            }
+            None => {
+                if !self.bits.has_packet()? {
+                    return Ok(None);
+                }
+            }
+        }
+
+        if let Some(OpenOperator {
+            limit: PacketLimit::SubPackets(remaining),
+            ..
+        }) = self.open.last_mut()
+        {
+            *remaining -= 1;
+        }
+
+        let version = self.bits.read_bits(3)? as u8;
+        let type_id = self.bits.read_bits(3)? as u8;
+
+        if type_id == 4 {
+            let mut value: u64 = 0;
+            loop {
+                let group = self.bits.read_bits(5)?;
+                if value >> 60 != 0 {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Literal value does not fit in 64 bits",
+                    ));
+                }
+                value = (value << 4) | (group & 0b1111);
+                if group >> 4 == 0 {
+                    break;
+                }
+            }
+            return Ok(Some(PacketEvent::Literal { version, value }));
+        }
+
+        let limit = if self.bits.read_bits(1)? == 0 {
+            let length = self.bits.read_bits(15)? as usize;
+            PacketLimit::EndPosition(self.bits.position + length)
+        } else {
+            PacketLimit::SubPackets(self.bits.read_bits(11)?)
+        };
+        self.open.push(OpenOperator { type_id, limit });
+        return Ok(Some(PacketEvent::EnterOperator { version, type_id }));
+    }
+}
+
+impl<R: Read> Iterator for PacketStream<R> {
+    type Item = io::Result<PacketEvent>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let event = self.next_event().transpose();
+        if !matches!(event, Some(Ok(_))) {
+            self.finished = true;
+        }
+        return event;
+    }
+}
+
+/// Builds every top level packet in the transmission from a `PacketStream`.
+pub fn decode_reader(reader: impl Read) -> io::Result<Vec<Packet>> {
+    let mut packets = vec![];
+    let mut open: Vec<(u8, u8, Vec<Packet>)> = vec![];
+    for event in PacketStream::new(reader) {
+        let packet = match event? {
+            PacketEvent::EnterOperator { version, type_id } => {
+                open.push((version, type_id, vec![]));
+                continue;
+            }
+            PacketEvent::Literal { version, value } => Packet {
+                version,
+                type_id: 4,
+                message: Message::LiretalValue { value },
+            },
+            PacketEvent::ExitOperator { .. } => {
+                let (version, type_id, sub_packets) = open.pop().unwrap();
+                Packet {
+                    version,
+                    type_id,
+                    message: Message::Operator { sub_packets },
+                }
+            }
+        };
+        match open.last_mut() {
+            Some((_, _, sub_packets)) => sub_packets.push(packet),
+            None => packets.push(packet),
+        }
+    }
+    if !open.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "Transmission ended inside an operator",
+        ));
+    }
+    return Ok(packets);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -268,6 +525,61 @@ mod tests {
         assert_eq!(version_sum, aoc_16_part_2(encoded))
     }
 
+    #[rstest]
+    #[case("38006F45291200", vec![
+        PacketEvent::EnterOperator{version: 1, type_id: 6},
+        PacketEvent::Literal{version: 6, value: 10},
+        PacketEvent::Literal{version: 2, value: 20},
+        PacketEvent::ExitOperator{type_id: 6},
+    ])]
+    #[case("EE00D40C823060", vec![
+        PacketEvent::EnterOperator{version: 7, type_id: 3},
+        PacketEvent::Literal{version: 2, value: 1},
+        PacketEvent::Literal{version: 4, value: 2},
+        PacketEvent::Literal{version: 1, value: 3},
+        PacketEvent::ExitOperator{type_id: 3},
+    ])]
+    // Two literals (2021 and 15) back to back, followed by zero padding
+    #[case("D2FE2F8F000\n", vec![
+        PacketEvent::Literal{version: 6, value: 2021},
+        PacketEvent::Literal{version: 7, value: 15},
+    ])]
+    fn test_packet_stream(#[case] encoded: &str, #[case] expected_events: Vec<PacketEvent>) {
+        let events = PacketStream::new(encoded.as_bytes())
+            .collect::<io::Result<Vec<_>>>()
+            .unwrap();
+        assert_eq!(expected_events, events)
+    }
+
+    #[rstest]
+    #[case("D2FE2")]
+    #[case("38006F452")]
+    #[case("D2FG28")]
+    fn test_packet_stream_invalid(#[case] encoded: &str) {
+        assert!(decode_reader(encoded.as_bytes()).is_err())
+    }
+
+    #[rstest]
+    // 16 groups of ones fill all 64 bits, a leading zero group does not count
+    #[case("13FFFFFFFFFFFFFFFFFFBC", Some(u64::MAX))]
+    #[case("121FFFFFFFFFFFFFFFFFFDE", Some(u64::MAX))]
+    // A one followed by 16 zero groups needs 65 bits
+    #[case("12308421084210842108400", None)]
+    fn test_packet_stream_wide_literal(#[case] encoded: &str, #[case] expected: Option<u64>) {
+        let event = PacketStream::new(encoded.as_bytes()).next().unwrap();
+        match expected {
+            Some(value) => assert_eq!(event.unwrap(), PacketEvent::Literal { version: 0, value }),
+            None => assert_eq!(event.unwrap_err().kind(), io::ErrorKind::InvalidData),
+        }
+    }
+
+    #[rstest]
+    fn test_decode_reader_actual_input() {
+        let input = read_file("src/input");
+        let packets = decode_reader(File::open("src/input").unwrap()).unwrap();
+        assert_eq!(vec![decode_str(input.trim())], packets);
+    }
+
     #[rstest]
     fn test_actual_input() {
         let input = read_file("src/input");
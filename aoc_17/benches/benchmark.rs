@@ -30,6 +30,12 @@ fn speed_test_grid_search(c: &mut Criterion) {
     group.bench_function("day 17 grid search smart mt", |b| {
         b.iter(|| grid_search_smart_mt(None, &target))
     });
+    group.bench_function("day 17 grid search parallel", |b| {
+        b.iter(|| grid_search_parallel(None, &target, None))
+    });
+    group.bench_function("day 17 closed form", |b| {
+        b.iter(|| solve_velocities(black_box(&target)))
+    });
 }
 
 criterion_group!(benches, speed_test_day_17, speed_test_grid_search);
@@ -2,7 +2,7 @@ use aoc_17::aoc_17;
 
 fn main() {
     let input_str = "target area: x=240..292, y=-90..-57";
-    let (best_y, num) = aoc_17(input_str, None);
+    let (best_y, num) = aoc_17(input_str, None).unwrap();
     println!("Part 1: {:?}", best_y);
     println!("Part 2: {:?}", num);
 }
@@ -1,40 +1,49 @@
 use itertools::Itertools;
 use std::cmp::max;
+use std::collections::HashSet;
 
-const GRID: ((i32, i32), (i32, i32)) = ((-100, 500), (-100, 500));
+/// Inclusive x and y ranges of the target area, `((x_lo, x_hi), (y_lo, y_hi))`.
+pub type Target = ((i32, i32), (i32, i32));
 
-pub fn aoc_17_comp(input: &str) -> (i32, u32) {
+/// Launch velocities to search, `((dx_start, dx_end), (dy_start, dy_end))` with the ends
+/// exclusive.
+pub type Grid = ((i32, i32), (i32, i32));
+
+/// Highest point reached and the launch velocity that hits the target.
+pub type Hit = (i32, (i32, i32));
+
+pub fn aoc_17_comp(input: &str) -> Result<(i32, u32), Unbounded> {
     let input = input.trim_end();
     return aoc_17(input, None);
 }
 
-pub fn aoc_17(input: &str, grid: Option<((i32, i32), (i32, i32))>) -> (i32, u32) {
+pub fn aoc_17(input: &str, grid: Option<Grid>) -> Result<(i32, u32), Unbounded> {
     let target = parse_input_str(input);
-    let all_locs = grid_search_smart_mt(grid, &target);
+    let all_locs = find_velocities(grid, &target)?;
     let best_loc = all_locs
         .iter()
         .max_by(|(left_y, _), (right_y, _)| left_y.cmp(right_y))
         .unwrap();
-    return (best_loc.0, all_locs.len() as u32);
+    return Ok((best_loc.0, all_locs.len() as u32));
 }
 
-pub fn aoc_17_part_1(input: &str, grid: Option<((i32, i32), (i32, i32))>) -> (i32, (i32, i32)) {
+pub fn aoc_17_part_1(input: &str, grid: Option<Grid>) -> Result<Hit, Unbounded> {
     let target = parse_input_str(input);
-    let all_locs = grid_search_smart_mt(grid, &target);
+    let all_locs = find_velocities(grid, &target)?;
     let best_loc = all_locs
         .iter()
         .max_by(|(left_y, _), (right_y, _)| left_y.cmp(right_y))
         .unwrap();
-    return *best_loc;
+    return Ok(*best_loc);
 }
 
-pub fn aoc_17_part_2(input: &str, grid: Option<((i32, i32), (i32, i32))>) -> u32 {
+pub fn aoc_17_part_2(input: &str, grid: Option<Grid>) -> Result<u32, Unbounded> {
     let target = parse_input_str(input);
-    let all_locs = grid_search_smart_mt(grid, &target);
-    return all_locs.len() as u32;
+    let all_locs = find_velocities(grid, &target)?;
+    return Ok(all_locs.len() as u32);
 }
 
-pub fn parse_input_str(input: &str) -> ((i32, i32), (i32, i32)) {
+pub fn parse_input_str(input: &str) -> Target {
     let (x_range, y_range) = input
         .trim_start_matches("target area: x=")
         .split_once(", y=")
@@ -52,11 +61,154 @@ pub fn parse_input_str(input: &str) -> ((i32, i32), (i32, i32)) {
     return ((x_left, x_right), (y_bot, y_top));
 }
 
-pub fn grid_search_naive(
-    grid: Option<((i32, i32), (i32, i32))>,
-    target: &((i32, i32), (i32, i32)),
-) -> Vec<(i32, (i32, i32))> {
-    let grid = grid.unwrap_or(GRID);
+/// Searches `grid` when given, otherwise solves for the velocities exactly. Without a grid
+/// a target that infinitely many velocities hit is an error.
+fn find_velocities(grid: Option<Grid>, target: &Target) -> Result<Vec<Hit>, Unbounded> {
+    return match grid {
+        Some(grid) => Ok(grid_search_smart_mt(Some(grid), target)),
+        None => solve_velocities(target),
+    };
+}
+
+/// The target can be hit by infinitely many velocities. This happens when the launcher is
+/// inside the target, or when the probe can stall above the target and fall into it from
+/// any height.
+#[derive(Debug, PartialEq)]
+pub struct Unbounded;
+
+fn triangle(n: i32) -> i32 {
+    return n * (n + 1) / 2;
+}
+
+/// Smallest `n >= 0` with `triangle(n) >= value`.
+fn triangle_root_ceil(value: i32) -> i32 {
+    let mut n = ((8.0 * value.max(0) as f64 + 1.0).sqrt() as i32 - 1) / 2;
+    while triangle(n) < value {
+        n += 1;
+    }
+    while n > 0 && triangle(n - 1) >= value {
+        n -= 1;
+    }
+    return n;
+}
+
+fn div_floor(a: i32, b: i32) -> i32 {
+    return a.div_euclid(b);
+}
+
+fn div_ceil(a: i32, b: i32) -> i32 {
+    return -(-a).div_euclid(b);
+}
+
+/// Positive `dx` that are inside `lo..=hi` after exactly `steps` steps, for `lo >= 1`. The
+/// probe either stalls at `triangle(dx)` when `dx <= steps`, or is still moving.
+fn dx_after_steps(steps: i32, (lo, hi): (i32, i32)) -> (i32, i32) {
+    let stalled = (
+        triangle_root_ceil(lo),
+        (triangle_root_ceil(hi + 1) - 1).min(steps),
+    );
+    let travelled = triangle(steps - 1);
+    let moving = (
+        div_ceil(lo + travelled, steps).max(steps),
+        div_floor(hi + travelled, steps),
+    );
+    // x after `steps` steps grows with dx, so both parts join into one range
+    return match (stalled.0 <= stalled.1, moving.0 <= moving.1) {
+        (true, true) => (stalled.0, moving.1),
+        (true, false) => stalled,
+        _ => moving,
+    };
+}
+
+/// Every `dx` that is inside the target after exactly `steps` steps, the target may lie on
+/// either side of the launcher.
+fn dx_candidates(steps: i32, (lo, hi): (i32, i32)) -> Vec<i32> {
+    let mut candidates = vec![];
+    if hi >= 1 {
+        let (start, end) = dx_after_steps(steps, (lo.max(1), hi));
+        candidates.extend(start..=end);
+    }
+    if lo <= -1 {
+        let (start, end) = dx_after_steps(steps, ((-hi).max(1), -lo));
+        candidates.extend((start..=end).map(|dx| -dx));
+    }
+    if lo <= 0 && 0 <= hi {
+        candidates.push(0);
+    }
+    return candidates;
+}
+
+/// Bounds of every `dx` and `dy` that hits the target, with the ends exclusive like a grid.
+pub fn velocity_bounds(target: &Target) -> Result<Grid, Unbounded> {
+    let ((x_lo, x_hi), (y_lo, y_hi)) = *target;
+    let dx = if x_lo > 0 {
+        (triangle_root_ceil(x_lo), x_hi + 1)
+    } else if x_hi < 0 {
+        (x_lo, 1 - triangle_root_ceil(-x_hi))
+    } else {
+        (x_lo, x_hi + 1)
+    };
+    let dy = if y_lo > 0 {
+        // On the way up every position above the launcher is passed, overshooting with the
+        // first step misses for good
+        (1, y_hi + 1)
+    } else if y_hi < 0 {
+        // The probe passes y = 0 again with speed dy + 1 on the way down
+        (y_lo, -y_lo)
+    } else {
+        let stalls =
+            (x_lo..=x_hi).any(|x| x == 0 || triangle(triangle_root_ceil(x.abs())) == x.abs());
+        if stalls {
+            return Err(Unbounded);
+        }
+        // Without stalling the probe is only in the target while still moving sideways, the
+        // highest dy can come from any of those steps
+        let steps = x_lo.abs().max(x_hi.abs());
+        let dy_max = (1..=steps)
+            .map(|steps| div_floor(y_hi + triangle(steps - 1), steps))
+            .max()
+            .unwrap_or(y_hi);
+        (y_lo, dy_max.max(y_hi) + 1)
+    };
+    return Ok((dx, dy));
+}
+
+/// All velocities that hit the target and their highest point, without searching a grid.
+/// For every number of steps the valid `dx` follow from triangular numbers and the valid `dy`
+/// from solving the height after that many steps.
+pub fn solve_velocities(target: &Target) -> Result<Vec<Hit>, Unbounded> {
+    let ((x_lo, x_hi), (y_lo, y_hi)) = *target;
+    let (_, (dy_lo, dy_hi)) = velocity_bounds(target)?;
+    let max_steps = if y_lo > 0 {
+        2 * y_hi + 1
+    } else if y_hi < 0 {
+        -2 * y_lo
+    } else {
+        x_lo.abs().max(x_hi.abs())
+    };
+
+    let mut velocities = HashSet::new();
+    for steps in 1..=max_steps {
+        let fallen = triangle(steps - 1);
+        let dy_start = div_ceil(y_lo + fallen, steps).max(dy_lo);
+        let dy_end = div_floor(y_hi + fallen, steps).min(dy_hi - 1);
+        if dy_start > dy_end {
+            continue;
+        }
+        for dx in dx_candidates(steps, (x_lo, x_hi)) {
+            for dy in dy_start..=dy_end {
+                velocities.insert((dx, dy));
+            }
+        }
+    }
+    return Ok(velocities
+        .into_iter()
+        .map(|velocity| (calc_max_y(velocity), velocity))
+        .collect());
+}
+
+pub fn grid_search_naive(grid: Option<Grid>, target: &Target) -> Vec<Hit> {
+    let grid = grid.unwrap_or_else(|| velocity_bounds(target).unwrap());
 
     let mut all_good_vel = vec![];
     for dx in grid.0 .0..grid.0 .1 {
@@ -70,11 +222,8 @@ pub fn grid_search_naive(
     return all_good_vel;
 }
 
-pub fn grid_search_smart(
-    grid: Option<((i32, i32), (i32, i32))>,
-    target: &((i32, i32), (i32, i32)),
-) -> Vec<(i32, (i32, i32))> {
-    let grid = grid.unwrap_or(GRID);
+pub fn grid_search_smart(grid: Option<Grid>, target: &Target) -> Vec<Hit> {
+    let grid = grid.unwrap_or_else(|| velocity_bounds(target).unwrap());
 
     let mut possible_dx = vec![];
     let mut possible_dy = vec![];
@@ -102,11 +251,8 @@ pub fn grid_search_smart(
     return all_good_vel;
 }
 
-pub fn grid_search_smart_mt<'a>(
-    grid: Option<((i32, i32), (i32, i32))>,
-    target: &'a ((i32, i32), (i32, i32)),
-) -> Vec<(i32, (i32, i32))> {
-    let grid = grid.unwrap_or(GRID);
+pub fn grid_search_smart_mt<'a>(grid: Option<Grid>, target: &'a Target) -> Vec<Hit> {
+    let grid = grid.unwrap_or_else(|| velocity_bounds(target).unwrap());
     let grid_x = grid.0.clone();
     let target_c = target.clone();
     let dx_thread = std::thread::spawn(move || {
@@ -142,6 +288,57 @@ pub fn grid_search_smart_mt<'a>(
     return all_good_vel;
 }
 
+/// Like `grid_search_smart`, but the (dx, dy) candidates are spread over `workers` threads,
+/// all cores when `None`. Each worker takes every `workers`-th candidate so that slow and fast
+/// simulations are shared evenly.
+pub fn grid_search_parallel(
+    grid: Option<Grid>,
+    target: &Target,
+    workers: Option<usize>,
+) -> Vec<Hit> {
+    let grid = grid.unwrap_or_else(|| velocity_bounds(target).unwrap());
+    let possible_dx = (grid.0 .0..grid.0 .1)
+        .filter(|dx| reaches_target_x(*dx, &target.0))
+        .collect_vec();
+    let possible_dy = (grid.1 .0..grid.1 .1)
+        .filter(|dy| reaches_target_y(*dy, &target.1))
+        .collect_vec();
+
+    let candidates = possible_dx.len() * possible_dy.len();
+    let workers = workers
+        .unwrap_or_else(|| {
+            std::thread::available_parallelism()
+                .map(|n| n.get())
+                .unwrap_or(1)
+        })
+        .clamp(1, candidates.max(1));
+
+    return std::thread::scope(|scope| {
+        let (possible_dx, possible_dy) = (&possible_dx, &possible_dy);
+        let handles = (0..workers)
+            .map(|worker| {
+                scope.spawn(move || {
+                    let mut good_vel = vec![];
+                    for index in (worker..candidates).step_by(workers) {
+                        let velocity = (
+                            possible_dx[index / possible_dy.len()],
+                            possible_dy[index % possible_dy.len()],
+                        );
+                        if reaches_target(velocity, target) {
+                            good_vel.push((calc_max_y(velocity), velocity));
+                        }
+                    }
+                    good_vel
+                })
+            })
+            .collect_vec();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    });
+}
+
 fn reaches_target_x(mut dx: i32, target: &(i32, i32)) -> bool {
     let mut x = 0;
 
@@ -149,7 +346,7 @@ fn reaches_target_x(mut dx: i32, target: &(i32, i32)) -> bool {
         if target.0 <= x && x <= target.1 {
             return true;
         }
-        if target.1 < x || dx == 0 {
+        if dx == 0 || is_past(x, dx, target) {
             return false;
         }
         x += dx;
@@ -164,7 +361,8 @@ fn reaches_target_y(mut dy: i32, target: &(i32, i32)) -> bool {
         if target.0 <= y && y <= target.1 {
             return true;
         }
-        if y < target.1 {
+        // Below the target and no longer rising
+        if y < target.0 && dy <= 0 {
             return false;
         }
         y += dy;
@@ -172,13 +370,13 @@ fn reaches_target_y(mut dy: i32, target: &(i32, i32)) -> bool {
     }
 }
 
-fn reaches_target(mut velocity: (i32, i32), target: &((i32, i32), (i32, i32))) -> bool {
+fn reaches_target(mut velocity: (i32, i32), target: &Target) -> bool {
     let mut location = (0, 0);
     loop {
         if is_within_target(&location, &target) {
             return true;
         }
-        if is_overshot(&location, &target) {
+        if is_overshot(&location, velocity, &target) {
             return false;
         }
         location.0 += velocity.0;
@@ -192,15 +390,171 @@ fn calc_max_y(velocity: (i32, i32)) -> i32 {
     return max(0, (velocity.1 * (velocity.1 + 1)) / 2);
 }
 
-fn is_within_target(loc: &(i32, i32), target: &((i32, i32), (i32, i32))) -> bool {
+fn is_within_target(loc: &(i32, i32), target: &Target) -> bool {
     return target.0 .0 <= loc.0
         && loc.0 <= target.0 .1
         && target.1 .0 <= loc.1
         && loc.1 <= target.1 .1;
 }
 
-fn is_overshot(loc: &(i32, i32), target: &((i32, i32), (i32, i32))) -> bool {
-    return loc.0 > target.0 .1 || loc.1 < target.1 .0;
+/// Whether `x` is beyond the far side of the target when moving with `dx`.
+fn is_past(x: i32, dx: i32, target: &(i32, i32)) -> bool {
+    return (dx > 0 && x > target.1) || (dx < 0 && x < target.0);
+}
+
+/// The probe can not reach the target anymore, it moves away from the target columns, has
+/// stalled outside them or falls below the target.
+fn is_overshot(loc: &(i32, i32), velocity: (i32, i32), target: &Target) -> bool {
+    let outside_columns = loc.0 < target.0 .0 || target.0 .1 < loc.0;
+    return is_past(loc.0, velocity.0, &target.0)
+        || (velocity.0 == 0 && outside_columns)
+        || (loc.1 < target.1 .0 && velocity.1 <= 0);
+}
+
+/// Why a probe never entered the target.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Miss {
+    /// Stopped moving sideways before reaching the target columns
+    Undershot,
+    /// Moved past the far side of the target
+    Overshot,
+    /// Was above or below the target columns but stepped over the target rows
+    FellThrough,
+    /// Was launched sideways away from the target
+    WrongWay,
+}
+
+/// Path of a probe up to the step where it enters the target or can no longer reach it.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trajectory {
+    pub velocity: (i32, i32),
+    pub target: Target,
+    /// Position after every step, starting with the launcher at `(0, 0)`
+    pub positions: Vec<(i32, i32)>,
+    /// Step at which the probe is first inside the target
+    pub result: Result<usize, Miss>,
+}
+
+pub fn trajectory(velocity: (i32, i32), target: &Target) -> Trajectory {
+    let ((x_lo, x_hi), (y_lo, _)) = *target;
+    // Columns between the launcher and the target, none when the target spans the launcher
+    let is_short = |x: i32| (x_lo > 0 && x < x_lo) || (x_hi < 0 && x > x_hi);
+    let in_columns = |x: i32| x_lo <= x && x <= x_hi;
+    let short = match (x_lo > 0 && velocity.0 < 0) || (x_hi < 0 && velocity.0 > 0) {
+        true => Miss::WrongWay,
+        false => Miss::Undershot,
+    };
+
+    let mut positions = vec![(0, 0)];
+    let (mut location, mut speed) = ((0, 0), velocity);
+    let result = loop {
+        location = (location.0 + speed.0, location.1 + speed.1);
+        speed = (speed.0 - speed.0.signum(), speed.1 - 1);
+        positions.push(location);
+
+        if is_within_target(&location, target) {
+            break Ok(positions.len() - 1);
+        }
+        let moving_away = speed.0 == 0 || (speed.0 > 0) == (location.0 > x_hi);
+        if !in_columns(location.0) && !is_short(location.0) && moving_away {
+            break Err(Miss::Overshot);
+        }
+        if location.1 < y_lo && speed.1 < 0 {
+            break Err(match location.0 {
+                x if in_columns(x) => Miss::FellThrough,
+                x if is_short(x) => short,
+                _ => Miss::Overshot,
+            });
+        }
+        if speed.0 == 0 && is_short(location.0) && location.1 < y_lo {
+            break Err(short);
+        }
+    };
+
+    return Trajectory {
+        velocity,
+        target: *target,
+        positions,
+        result,
+    };
+}
+
+impl Trajectory {
+    /// Corners of the area covering the launcher, the target and every position.
+    fn extent(self: &Self) -> ((i32, i32), (i32, i32)) {
+        let ((x_lo, x_hi), (y_lo, y_hi)) = self.target;
+        return self.positions.iter().fold(
+            ((x_lo, y_lo), (x_hi, y_hi)),
+            |((min_x, min_y), (max_x, max_y)), &(x, y)| {
+                ((min_x.min(x), min_y.min(y)), (max_x.max(x), max_y.max(y)))
+            },
+        );
+    }
+
+    /// Draws the trajectory like the puzzle does, `S` is the launcher, `#` the probe and `T`
+    /// the target. The top row is the highest point.
+    pub fn to_ascii(self: &Self) -> String {
+        let ((min_x, min_y), (max_x, max_y)) = self.extent();
+        let probe = self.positions[1..].iter().collect::<HashSet<_>>();
+        let mut out = String::new();
+        for y in (min_y..=max_y).rev() {
+            for x in min_x..=max_x {
+                out.push(match (x, y) {
+                    (0, 0) => 'S',
+                    location if probe.contains(&location) => '#',
+                    location if is_within_target(&location, &self.target) => 'T',
+                    _ => '.',
+                });
+            }
+            out.push('\n');
+        }
+        return out;
+    }
+
+    /// The same drawing as an SVG, one unit per position.
+    pub fn to_svg(self: &Self) -> String {
+        let ((min_x, min_y), (max_x, max_y)) = self.extent();
+        let ((x_lo, x_hi), (y_lo, y_hi)) = self.target;
+        // SVG y grows downwards
+        let point = |(x, y): (i32, i32)| (x - min_x, max_y - y);
+        let (width, height) = (max_x - min_x + 1, max_y - min_y + 1);
+        let (target_x, target_y) = point((x_lo, y_hi));
+
+        let mut svg = format!(
+            "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"-1 -1 {} {}\">\n",
+            width + 1,
+            height + 1
+        );
+        svg.push_str(&format!(
+            "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"#c9e6c9\"/>\n",
+            target_x,
+            target_y,
+            x_hi - x_lo,
+            y_hi - y_lo
+        ));
+        let path = self
+            .positions
+            .iter()
+            .map(|&position| {
+                let (x, y) = point(position);
+                format!("{},{}", x, y)
+            })
+            .join(" ");
+        svg.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"#555\" stroke-width=\"0.1\"/>\n",
+            path
+        ));
+        for (step, &position) in self.positions.iter().enumerate() {
+            let (x, y) = point(position);
+            let fill = if step == 0 { "#36c" } else { "#c33" };
+            svg.push_str(&format!(
+                "<circle cx=\"{}\" cy=\"{}\" r=\"0.3\" fill=\"{}\"/>\n",
+                x, y, fill
+            ));
+        }
+        svg.push_str("</svg>\n");
+        return svg;
+    }
 }
 
 #[cfg(test)]
@@ -328,24 +682,145 @@ mod tests {
     #[case((27,-8), ((20, 30), (-10, -5)), true)]
     #[case((30,-5), ((20, 30), (-10, -5)), true)]
     #[case((24,-7), ((20, 30), (-10, -5)), true)]
-    fn test_reach(
-        #[case] start_vel: (i32, i32),
-        #[case] target: ((i32, i32), (i32, i32)),
-        #[case] reaches: bool,
-    ) {
+    fn test_reach(#[case] start_vel: (i32, i32), #[case] target: Target, #[case] reaches: bool) {
         assert_eq!(reaches_target(start_vel, &target), reaches)
     }
 
+    fn simulate_hits(velocity: (i32, i32), target: &Target) -> bool {
+        let (mut location, mut velocity) = ((0, 0), velocity);
+        for _ in 0..500 {
+            location = (location.0 + velocity.0, location.1 + velocity.1);
+            velocity = (velocity.0 - velocity.0.signum(), velocity.1 - 1);
+            if is_within_target(&location, target) {
+                return true;
+            }
+        }
+        return false;
+    }
+
+    #[rstest]
+    #[case(((20, 30), (-10, -5)))]
+    #[case(((-30, -20), (-10, -5)))]
+    #[case(((20, 30), (5, 10)))]
+    #[case(((-12, -3), (3, 4)))]
+    #[case(((-5, 5), (-10, -5)))]
+    #[case(((22, 27), (-5, 5)))]
+    #[case(((24, 25), (-14, 18)))]
+    #[case(((30, 34), (-19, 27)))]
+    #[case(((11, 12), (-3, 15)))]
+    #[case(((-5, -5), (-19, 25)))]
+    fn test_solve_velocities(#[case] target: Target) {
+        let mut expected = vec![];
+        for dx in -60..60 {
+            for dy in -60..200 {
+                if simulate_hits((dx, dy), &target) {
+                    expected.push((calc_max_y((dx, dy)), (dx, dy)));
+                }
+            }
+        }
+        let mut solved = solve_velocities(&target).unwrap();
+        solved.sort();
+        expected.sort();
+        assert_eq!(solved, expected);
+
+        let ((dx_lo, dx_hi), (dy_lo, dy_hi)) = velocity_bounds(&target).unwrap();
+        for (_, (dx, dy)) in &solved {
+            assert!(dx_lo <= *dx && *dx < dx_hi && dy_lo <= *dy && *dy < dy_hi);
+        }
+
+        let mut naive = grid_search_naive(None, &target);
+        let mut smart = grid_search_smart(None, &target);
+        naive.sort();
+        smart.sort();
+        assert_eq!(naive, solved);
+        assert_eq!(smart, solved);
+    }
+
+    #[rstest]
+    #[case(((20, 30), (-5, 5)))]
+    #[case(((-1, 1), (-1, 1)))]
+    fn test_solve_velocities_unbounded(#[case] target: Target) {
+        assert_eq!(solve_velocities(&target), Err(Unbounded));
+    }
+
+    #[rstest]
+    #[case("target area: x=20..30, y=-5..5")]
+    #[case("target area: x=-1..1, y=-1..1")]
+    fn test_unbounded_input(#[case] input: &str) {
+        assert_eq!(aoc_17(input, None), Err(Unbounded));
+        assert_eq!(aoc_17_comp(&format!("{}\n", input)), Err(Unbounded));
+        assert_eq!(aoc_17_part_1(input, None), Err(Unbounded));
+        assert_eq!(aoc_17_part_2(input, None), Err(Unbounded));
+        // A finite grid still counts the velocities inside it
+        assert!(aoc_17_part_2(input, Some(((-5, 40), (-10, 10)))).unwrap() > 0);
+    }
+
+    #[test]
+    fn test_solve_velocities_beyond_grid() {
+        // The old fixed grid stopped at a velocity of 500 in either direction
+        let target = ((1000, 1010), (-700, -650));
+        let solved = solve_velocities(&target).unwrap();
+        let (best_y, _) = solved.iter().max().unwrap();
+        assert_eq!(*best_y, 699 * 700 / 2);
+        assert_eq!(solved.len(), grid_search_smart(None, &target).len());
+    }
+
+    #[rstest]
+    #[case((7, 2), Ok(7))]
+    #[case((6, 3), Ok(9))]
+    #[case((9, 0), Ok(4))]
+    #[case((17, -4), Err(Miss::Overshot))]
+    #[case((5, 2), Err(Miss::Undershot))]
+    #[case((6, 10), Err(Miss::FellThrough))]
+    #[case((-3, 2), Err(Miss::WrongWay))]
+    #[case((-1, -1), Err(Miss::WrongWay))]
+    fn test_trajectory(#[case] velocity: (i32, i32), #[case] expected: Result<usize, Miss>) {
+        let target = ((20, 30), (-10, -5));
+        let trajectory = trajectory(velocity, &target);
+        assert_eq!(trajectory.result, expected);
+        assert_eq!(trajectory.result.is_ok(), reaches_target(velocity, &target));
+        if velocity.1 > 0 {
+            let highest = trajectory.positions.iter().map(|(_, y)| *y).max().unwrap();
+            assert_eq!(highest, calc_max_y(velocity));
+        }
+    }
+
+    #[test]
+    fn test_trajectory_ascii() {
+        let expected = "\
+.............#....#............
+.......#..............#........
+...............................
+S........................#.....
+...............................
+...............................
+...........................#...
+...............................
+....................TTTTTTTTTTT
+....................TTTTTTTTTTT
+....................TTTTTTTT#TT
+....................TTTTTTTTTTT
+....................TTTTTTTTTTT
+....................TTTTTTTTTTT
+";
+        let trajectory = trajectory((7, 2), &((20, 30), (-10, -5)));
+        assert_eq!(trajectory.to_ascii(), expected);
+
+        let svg = trajectory.to_svg();
+        assert!(svg.starts_with("<svg"));
+        assert_eq!(svg.matches("<circle").count(), trajectory.positions.len());
+    }
+
     #[rstest]
     fn test_grid_search() {
-        let (best_y, _) = aoc_17_part_1("target area: x=20..30, y=-10..-5", None);
+        let (best_y, _) = aoc_17_part_1("target area: x=20..30, y=-10..-5", None).unwrap();
         assert_eq!(best_y, 45);
     }
 
     #[rstest]
     fn test_smart_grid_search() {
         let target = ((20, 30), (-10, -5));
-        let grid = GRID;
+        let grid = velocity_bounds(&target).unwrap();
         let mut all_naive = grid_search_naive(Some(grid), &target);
         let mut all_smart = grid_search_smart(Some(grid), &target);
         all_naive.sort();
@@ -356,7 +831,7 @@ mod tests {
     #[rstest]
     fn test_smart_grid_search_mt() {
         let target = ((20, 30), (-10, -5));
-        let grid = GRID;
+        let grid = velocity_bounds(&target).unwrap();
         let mut all_smart = grid_search_smart(Some(grid), &target);
         let mut all_smart_mt = grid_search_smart_mt(Some(grid), &target);
         all_smart.sort();
@@ -364,10 +839,43 @@ mod tests {
         assert_eq!(all_smart, all_smart_mt)
     }
 
+    #[rstest]
+    #[case(((20, 30), (-10, -5)), Some(1))]
+    #[case(((20, 30), (-10, -5)), Some(3))]
+    #[case(((20, 30), (-10, -5)), None)]
+    #[case(((240, 292), (-90, -57)), Some(4))]
+    #[case(((-30, -20), (5, 10)), Some(2))]
+    fn test_grid_search_parallel(#[case] target: Target, #[case] workers: Option<usize>) {
+        let grid = velocity_bounds(&target).unwrap();
+        let mut all_naive = grid_search_naive(Some(grid), &target);
+        let mut all_parallel = grid_search_parallel(Some(grid), &target, workers);
+        let mut solved = solve_velocities(&target).unwrap();
+        all_naive.sort();
+        all_parallel.sort();
+        solved.sort();
+        assert!(!solved.is_empty());
+        assert_eq!(all_naive, all_parallel);
+        assert_eq!(all_parallel, solved)
+    }
+
+    #[rstest]
+    fn test_grid_search_parallel_more_workers_than_candidates() {
+        let target = ((20, 30), (-10, -5));
+        let grid = ((6, 7), (9, 10));
+        assert_eq!(
+            grid_search_parallel(Some(grid), &target, Some(64)),
+            vec![(45, (6, 9))]
+        );
+        assert_eq!(
+            grid_search_parallel(Some(((0, 1), (0, 1))), &target, Some(64)),
+            vec![]
+        );
+    }
+
     #[rstest]
     fn test_example_input() {
         let input_str = "target area: x=20..30, y=-10..-5";
-        let (best_y, num) = aoc_17(input_str, None);
+        let (best_y, num) = aoc_17(input_str, None).unwrap();
         assert_eq!(best_y, 45);
         assert_eq!(num, 112)
     }
@@ -375,7 +883,7 @@ mod tests {
     #[rstest]
     fn test_actual_input() {
         let input_str = "target area: x=240..292, y=-90..-57";
-        let (best_y, num) = aoc_17(input_str, None);
+        let (best_y, num) = aoc_17(input_str, None).unwrap();
         assert_eq!(best_y, 4005);
         assert_eq!(num, 2953)
     }
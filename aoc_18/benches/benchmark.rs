@@ -1 +1,40 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use std::fs::File;
+use std::io::{BufReader, Read};
+use std::str::FromStr;
 
+extern crate aoc_18;
+use aoc_18::*;
+
+fn read_file(file: &str) -> String {
+    let input = File::open(file).unwrap();
+
+    let mut contents = String::new();
+    BufReader::new(input).read_to_string(&mut contents).unwrap();
+
+    return contents;
+}
+
+fn speed_test_day_18(c: &mut Criterion) {
+    let input_str = read_file("src/input");
+    c.bench_function("day 18 part 1", |b| {
+        b.iter(|| aoc_18_part_1(black_box(&input_str)))
+    });
+    c.bench_function("day 18 part 2", |b| {
+        b.iter(|| aoc_18_part_2(black_box(&input_str)))
+    });
+
+    let numbers = parse_input_str(&input_str);
+    c.bench_function("day 18 best 10 pairs", |b| {
+        b.iter(|| best_pairs(black_box(&numbers), 10))
+    });
+
+    let lhs = SnailFishNumber::from_str(input_str.lines().next().unwrap()).unwrap();
+    let rhs = SnailFishNumber::from_str(input_str.lines().nth(1).unwrap()).unwrap();
+    c.bench_function("day 18 add pair", |b| {
+        b.iter(|| (black_box(&lhs) + black_box(&rhs)).magnitude())
+    });
+}
+
+criterion_group!(benches, speed_test_day_18);
+criterion_main!(benches);
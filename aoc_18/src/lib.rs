@@ -1,5 +1,8 @@
-use std::fmt::Debug;
-use std::{ops::Add, str::FromStr, string::ParseError};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt::{Debug, Display};
+use std::ops::{Add, AddAssign, Div, Rem};
+use std::str::FromStr;
 
 pub fn parse_input_str(input: &str) -> Vec<SnailFishNumber> {
     return input
@@ -23,242 +26,506 @@ pub fn aoc_18_part_1(input: &str) -> u32 {
 }
 
 pub fn aoc_18_part_2(input: &str) -> u32 {
-    let numbers_1 = parse_input_str(input);
-    let numbers_2 = parse_input_str(input);
+    let numbers = parse_input_str(input);
+    return best_pair(&numbers).unwrap().magnitude;
+}
 
-    let mut max_magnitude = 0;
+/// Magnitude of `numbers[lhs] + numbers[rhs]`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct PairMagnitude {
+    pub magnitude: u32,
+    pub lhs: usize,
+    pub rhs: usize,
+}
 
-    for lhs in numbers_1 {
-        for rhs in &numbers_2 {
-            let magnitude = (lhs.clone() + rhs.clone()).magnitude();
-            if magnitude > max_magnitude {
-                max_magnitude = magnitude;
-            }
-        }
+/// The pair of different numbers with the largest sum magnitude,
+/// or None if there are less than two numbers.
+pub fn best_pair<T: LeafValue + Send + Sync>(
+    numbers: &[SnailFishNumberOf<T>],
+) -> Option<PairMagnitude> {
+    return best_pairs(numbers, 1).pop();
+}
+
+/// The `k` ordered pairs of different numbers with the largest sum magnitude, best first.
+/// Ties are broken on the lowest indices. The rows of the search are spread over all cores.
+pub fn best_pairs<T: LeafValue + Send + Sync>(
+    numbers: &[SnailFishNumberOf<T>],
+    k: usize,
+) -> Vec<PairMagnitude> {
+    let workers = std::thread::available_parallelism()
+        .map(|n| n.get())
+        .unwrap_or(1)
+        .min(numbers.len().max(1));
+
+    let mut pairs: Vec<PairMagnitude> = std::thread::scope(|scope| {
+        let handles = (0..workers)
+            .map(|worker| {
+                scope.spawn(move || {
+                    // Min heap holding the best k pairs this worker has seen so far
+                    let mut best = BinaryHeap::with_capacity(k + 1);
+                    for lhs in (worker..numbers.len()).step_by(workers) {
+                        for rhs in 0..numbers.len() {
+                            if lhs == rhs {
+                                continue;
+                            }
+                            let magnitude = (&numbers[lhs] + &numbers[rhs]).magnitude();
+                            best.push(Reverse((magnitude, Reverse(lhs), Reverse(rhs))));
+                            if best.len() > k {
+                                best.pop();
+                            }
+                        }
+                    }
+                    best
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .map(
+                |Reverse((magnitude, Reverse(lhs), Reverse(rhs)))| PairMagnitude {
+                    magnitude,
+                    lhs,
+                    rhs,
+                },
+            )
+            .collect()
+    });
+
+    pairs.sort_by(|a, b| {
+        b.magnitude
+            .cmp(&a.magnitude)
+            .then(a.lhs.cmp(&b.lhs))
+            .then(a.rhs.cmp(&b.rhs))
+    });
+    pairs.truncate(k);
+    return pairs;
+}
+
+/// Integer type that can be stored in the leaves of a snailfish number.
+pub trait LeafValue:
+    Copy
+    + PartialOrd
+    + Add<Output = Self>
+    + AddAssign
+    + Div<Output = Self>
+    + Rem<Output = Self>
+    + From<u8>
+    + Into<u32>
+    + TryFrom<u32>
+    + Display
+    + Debug
+{
+    /// Sum of two leaves, None if it does not fit in the leaf type.
+    fn checked_sum(self, rhs: Self) -> Option<Self> {
+        let sum = self.into().checked_add(rhs.into())?;
+        return Self::try_from(sum).ok();
     }
+}
 
-    return max_magnitude;
+impl<T> LeafValue for T where
+    T: Copy
+        + PartialOrd
+        + Add<Output = Self>
+        + AddAssign
+        + Div<Output = Self>
+        + Rem<Output = Self>
+        + From<u8>
+        + Into<u32>
+        + TryFrom<u32>
+        + Display
+        + Debug
+{
 }
 
+/// Deepest leaf the format allows, the leaves of a pair nested inside four pairs. Reduced
+/// numbers stay one level above it.
+const MAX_DEPTH: u8 = 5;
+
+const LEAF_OVERFLOW: &str = "snailfish leaf does not fit in the leaf type";
+
+/// A snailfish number stored as its leaves from left to right, each tagged with its depth.
+/// The pair structure is implied by the depths, use `to_tree` to recover it.
 #[derive(PartialEq, Clone)]
-pub enum SnailFishNumber {
-    NODE { children: Vec<SnailFishNumber> },
-    LEAF { val: u8 },
+pub struct SnailFishNumberOf<T: LeafValue> {
+    leaves: Vec<Leaf<T>>,
 }
 
-impl ToString for SnailFishNumber {
-    fn to_string(&self) -> String {
-        let mut string = String::new();
-        match &self {
-            &SnailFishNumber::NODE { children } => {
-                string.push('[');
-                string.push_str(&children[0].to_string());
-                string.push(',');
-                string.push_str(&children[1].to_string());
-                string.push(']');
-            }
-            &SnailFishNumber::LEAF { val } => string.push_str(&val.to_string()),
+/// Snailfish number with single byte leaves, enough for all puzzle inputs.
+pub type SnailFishNumber = SnailFishNumberOf<u8>;
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+struct Leaf<T> {
+    val: T,
+    depth: u8,
+}
+
+/// A single action taken while reducing a snailfish number.
+/// `index` is the position of the (left) value among the leaves, counted from the left.
+#[derive(PartialEq, Clone, Debug)]
+pub enum ReductionAction<T = u8> {
+    Addition,
+    Explode { index: usize, pair: (T, T) },
+    Split { index: usize, val: T },
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub struct ReductionStep<T: LeafValue = u8> {
+    pub action: ReductionAction<T>,
+    pub number: SnailFishNumberOf<T>,
+}
+
+impl<T: LeafValue> Display for ReductionStep<T> {
+    /// Formats the step the way the puzzle statement does, e.g. `after explode:  [[0,7],4]`
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let action = match self.action {
+            ReductionAction::Addition => "after addition:",
+            ReductionAction::Explode { .. } => "after explode:",
+            ReductionAction::Split { .. } => "after split:",
+        };
+        write!(f, "{:<16}{}", action, self.number)
+    }
+}
+
+/// Tree view of a `SnailFishNumber`.
+#[derive(PartialEq, Clone)]
+pub enum SnailFishTree<T = u8> {
+    NODE { children: Vec<SnailFishTree<T>> },
+    LEAF { val: T },
+}
+
+impl<T: LeafValue> Display for SnailFishTree<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnailFishTree::NODE { children } => write!(f, "[{},{}]", children[0], children[1]),
+            SnailFishTree::LEAF { val } => write!(f, "{}", val),
         }
-        return string;
     }
 }
 
-impl Debug for SnailFishNumber {
+impl<T: LeafValue> Debug for SnailFishTree<T> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("SnailFishNumber")
+        f.debug_struct("SnailFishTree")
             .field("data", &self.to_string())
             .finish()
     }
 }
 
-#[derive(Debug)]
-enum Explosion {
-    NO,
-    Left(u8),
-    Right(u8),
-    Explosion(u8, u8),
-    Handled,
-}
+impl<T: LeafValue> SnailFishTree<T> {
+    pub fn magnitude(&self) -> u32 {
+        match self {
+            &Self::LEAF { val } => return val.into(),
+            Self::NODE { children } => {
+                return 3 * children[0].magnitude() + 2 * children[1].magnitude();
+            }
+        }
+    }
 
-impl SnailFishNumber {
-    fn new_node(left: SnailFishNumber, right: SnailFishNumber) -> SnailFishNumber {
-        return SnailFishNumber::NODE {
+    fn _from_leaves(leaves: &[Leaf<T>], index: &mut usize, depth: u8) -> SnailFishTree<T> {
+        let leaf = leaves[*index];
+        if leaf.depth == depth {
+            *index += 1;
+            return SnailFishTree::LEAF { val: leaf.val };
+        }
+        let left = SnailFishTree::_from_leaves(leaves, index, depth + 1);
+        let right = SnailFishTree::_from_leaves(leaves, index, depth + 1);
+        return SnailFishTree::NODE {
             children: vec![left, right],
         };
     }
+}
 
-    fn new_value(val: u8) -> SnailFishNumber {
-        return SnailFishNumber::LEAF { val };
+impl<T: LeafValue> From<&SnailFishTree<T>> for SnailFishNumberOf<T> {
+    fn from(tree: &SnailFishTree<T>) -> Self {
+        match tree {
+            &SnailFishTree::LEAF { val } => return SnailFishNumberOf::new_value(val),
+            SnailFishTree::NODE { children } => {
+                return SnailFishNumberOf::new_node(
+                    SnailFishNumberOf::from(&children[0]),
+                    SnailFishNumberOf::from(&children[1]),
+                );
+            }
+        }
     }
+}
 
-    fn _is_node(self: &Self) -> bool {
-        match self {
-            SnailFishNumber::NODE { .. } => true,
-            _ => false,
-        }
+impl<T: LeafValue> Display for SnailFishNumberOf<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_tree())
     }
+}
 
-    fn _add_left(self: &mut Self, new_val: u8) {
-        match self {
-            SnailFishNumber::LEAF { val } => *val += new_val,
-            SnailFishNumber::NODE { children } => children[0]._add_left(new_val),
+impl<T: LeafValue> Debug for SnailFishNumberOf<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SnailFishNumber")
+            .field("data", &self.to_string())
+            .finish()
+    }
+}
+
+impl<T: LeafValue> SnailFishNumberOf<T> {
+    fn new_node(left: SnailFishNumberOf<T>, right: SnailFishNumberOf<T>) -> SnailFishNumberOf<T> {
+        let mut leaves = left.leaves;
+        leaves.extend(right.leaves);
+        for leaf in leaves.iter_mut() {
+            leaf.depth += 1;
         }
+        return SnailFishNumberOf { leaves };
     }
 
-    fn _add_rigth(self: &mut Self, new_val: u8) {
-        match self {
-            SnailFishNumber::LEAF { val } => *val += new_val,
-            SnailFishNumber::NODE { children } => children[1]._add_rigth(new_val),
+    fn new_value(val: T) -> SnailFishNumberOf<T> {
+        return SnailFishNumberOf {
+            leaves: vec![Leaf { val, depth: 0 }],
+        };
+    }
+
+    pub fn to_tree(&self) -> SnailFishTree<T> {
+        return SnailFishTree::_from_leaves(&self.leaves, &mut 0, 0);
+    }
+
+    /// Explodes the leftmost pair nested inside four pairs.
+    /// Returns None if there is no such pair.
+    fn _explode(&mut self) -> Option<ReductionAction<T>> {
+        let index = self.leaves.iter().position(|leaf| leaf.depth > 4)?;
+        let Leaf { val: left, depth } = self.leaves[index];
+        let right = self.leaves.remove(index + 1).val;
+        if index > 0 {
+            let previous = &mut self.leaves[index - 1];
+            previous.val = previous.val.checked_sum(left).expect(LEAF_OVERFLOW);
         }
+        if let Some(next) = self.leaves.get_mut(index + 1) {
+            next.val = next.val.checked_sum(right).expect(LEAF_OVERFLOW);
+        }
+        self.leaves[index] = Leaf {
+            val: T::from(0),
+            depth: depth - 1,
+        };
+        return Some(ReductionAction::Explode {
+            index,
+            pair: (left, right),
+        });
     }
 
-    fn _explode(self: &mut Self, depth: u8) -> Explosion {
-        match self {
-            SnailFishNumber::LEAF { .. } => return Explosion::NO,
-            SnailFishNumber::NODE { children } => {
-                assert_eq!(children.len(), 2);
-                if depth >= 4 {
-                    // Handle explosion
-                    let mut numbers = vec![];
-                    for child in children {
-                        if let SnailFishNumber::LEAF { val } = child {
-                            numbers.push(val);
-                        } else {
-                            panic!()
-                        }
-                    }
-                    return Explosion::Explosion(*numbers[0], *numbers[1]);
-                } else {
-                    // Handle recursion
-                    // Handle left child
-                    let left = children[0]._explode(depth + 1);
-                    match left {
-                        Explosion::Handled => return Explosion::Handled,
-                        Explosion::NO => (),
-                        Explosion::Left(val) => return Explosion::Left(val),
-                        Explosion::Right(val) => {
-                            children[1]._add_left(val);
-                            return Explosion::Handled;
-                        }
-                        Explosion::Explosion(left, right) => {
-                            children[0] = SnailFishNumber::LEAF { val: 0 };
-                            children[1]._add_left(right);
-                            return Explosion::Left(left);
-                        }
-                    };
-
-                    let right = children[1]._explode(depth + 1);
-                    match right {
-                        Explosion::Handled => return Explosion::Handled,
-                        Explosion::NO => (),
-                        Explosion::Right(val) => return Explosion::Right(val),
-                        Explosion::Left(val) => {
-                            children[0]._add_rigth(val);
-                            return Explosion::Handled;
-                        }
-                        Explosion::Explosion(left, right) => {
-                            children[0]._add_rigth(left);
-                            children[1] = SnailFishNumber::LEAF { val: 0 };
-                            return Explosion::Right(right);
-                        }
-                    };
+    /// Splits the leftmost value of 10 or greater.
+    /// Returns None if there is no such value.
+    fn _split(&mut self) -> Option<ReductionAction<T>> {
+        let index = self
+            .leaves
+            .iter()
+            .position(|leaf| leaf.val >= T::from(10))?;
+        let Leaf { val, depth } = self.leaves[index];
+        let two = T::from(2);
+        self.leaves[index] = Leaf {
+            val: val / two,
+            depth: depth + 1,
+        };
+        self.leaves.insert(
+            index + 1,
+            Leaf {
+                // Trick to do ceil division
+                val: (val / two) + (val % two),
+                depth: depth + 1,
+            },
+        );
+        return Some(ReductionAction::Split { index, val });
+    }
+
+    /// Applies the next reduction action, explosions take precedence over splits.
+    fn _reduce_step(&mut self) -> Option<ReductionAction<T>> {
+        return self._explode().or_else(|| self._split());
+    }
+
+    fn _reduce(&mut self) {
+        while self._reduce_step().is_some() {}
+    }
+
+    /// Adds `rhs` to this number and returns every intermediate step of the reduction,
+    /// starting with the unreduced sum. The last step holds the reduced result.
+    pub fn add_traced(&self, rhs: &SnailFishNumberOf<T>) -> Vec<ReductionStep<T>> {
+        let mut number = SnailFishNumberOf::new_node(self.clone(), rhs.clone());
+        let mut steps = vec![ReductionStep {
+            action: ReductionAction::Addition,
+            number: number.clone(),
+        }];
+        while let Some(action) = number._reduce_step() {
+            steps.push(ReductionStep {
+                action,
+                number: number.clone(),
+            });
+        }
+        return steps;
+    }
 
-                    return Explosion::NO;
+    pub fn magnitude(&self) -> u32 {
+        // Repeatedly combine the two rightmost values while they form a pair
+        let mut stack: Vec<(u32, u8)> = vec![];
+        for leaf in &self.leaves {
+            let mut current = (leaf.val.into(), leaf.depth);
+            while let Some(&(left, depth)) = stack.last() {
+                if depth != current.1 {
+                    break;
                 }
+                stack.pop();
+                current = (3 * left + 2 * current.0, depth - 1);
             }
+            stack.push(current);
         }
+        return stack[0].0;
     }
 }
 
-enum Split {
-    Handled,
-    No,
-    Split(u8),
+/// Reasons a snailfish number failed to parse, each with the byte offset of the problem.
+#[derive(Debug, PartialEq)]
+pub enum ParseSnailFishError {
+    /// The `[` at `offset` is not closed where its pair should end
+    UnclosedBracket {
+        offset: usize,
+    },
+    /// The `]` at `offset` has no matching `[`
+    UnmatchedBracket {
+        offset: usize,
+    },
+    MissingComma {
+        offset: usize,
+    },
+    InvalidLeaf {
+        offset: usize,
+    },
+    /// The literal starting at `offset` does not fit in the leaf type
+    LeafOverflow {
+        offset: usize,
+    },
+    /// The `[` at `offset` opens a pair nested inside more than four pairs
+    TooDeep {
+        offset: usize,
+    },
 }
 
-impl SnailFishNumber {
-    fn _split(&mut self) -> Split {
+impl Display for ParseSnailFishError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::NODE { children } => {
-                for child in children.iter_mut() {
-                    match child._split() {
-                        Split::Split(val) => {
-                            *child = SnailFishNumber::NODE {
-                                children: vec![
-                                    SnailFishNumber::LEAF { val: val / 2 },
-                                    SnailFishNumber::LEAF {
-                                        // Trick to do ceil division
-                                        val: (val / 2) + (val % 2 != 0) as u8,
-                                    },
-                                ],
-                            };
-                            return Split::Handled;
-                        }
-                        Split::Handled => return Split::Handled,
-                        Split::No => (),
-                    };
-                }
-                return Split::No;
-            }
-            Self::LEAF { val } => {
-                if *val >= 10 {
-                    return Split::Split(*val);
-                }
-                return Split::No;
-            }
+            Self::UnclosedBracket { offset } => write!(f, "unclosed '[' at byte {}", offset),
+            Self::UnmatchedBracket { offset } => write!(f, "unmatched ']' at byte {}", offset),
+            Self::MissingComma { offset } => write!(f, "expected ',' at byte {}", offset),
+            Self::InvalidLeaf { offset } => write!(f, "expected a digit at byte {}", offset),
+            Self::LeafOverflow { offset } => write!(f, "literal at byte {} is too large", offset),
+            Self::TooDeep { offset } => write!(f, "pair at byte {} is nested too deep", offset),
         }
     }
+}
 
-    pub fn magnitude(&self) -> u32 {
-        match self {
-            &Self::LEAF { val } => return val as u32,
-            Self::NODE { children } => {
-                return 3 * children[0].magnitude() + 2 * children[1].magnitude();
+/// Recursive descent parser that writes the leaves straight into the flat representation.
+struct Parser<'a, T> {
+    bytes: &'a [u8],
+    offset: usize,
+    leaves: Vec<Leaf<T>>,
+}
+
+impl<'a, T: LeafValue> Parser<'a, T> {
+    fn parse_element(&mut self, depth: u8) -> Result<(), ParseSnailFishError> {
+        if self.bytes.get(self.offset) != Some(&b'[') {
+            return self.parse_leaf(depth);
+        }
+        let open = self.offset;
+        if depth >= MAX_DEPTH {
+            return Err(ParseSnailFishError::TooDeep { offset: open });
+        }
+        self.offset += 1;
+        self.parse_element(depth + 1)?;
+        match self.bytes.get(self.offset) {
+            Some(b',') => self.offset += 1,
+            None => return Err(ParseSnailFishError::UnclosedBracket { offset: open }),
+            Some(b'[') | Some(b']') => {
+                return Err(ParseSnailFishError::MissingComma {
+                    offset: self.offset,
+                })
+            }
+            Some(_) => {
+                return Err(ParseSnailFishError::InvalidLeaf {
+                    offset: self.offset,
+                })
             }
         }
+        self.parse_element(depth + 1)?;
+        match self.bytes.get(self.offset) {
+            Some(b']') => self.offset += 1,
+            None | Some(b',') | Some(b'[') => {
+                return Err(ParseSnailFishError::UnclosedBracket { offset: open })
+            }
+            Some(_) => {
+                return Err(ParseSnailFishError::InvalidLeaf {
+                    offset: self.offset,
+                })
+            }
+        }
+        return Ok(());
     }
-}
-
-impl FromStr for SnailFishNumber {
-    type Err = ParseError;
 
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if s.len() == 1 {
-            return Ok(SnailFishNumber::new_value(s.parse::<u8>().unwrap()));
+    fn parse_leaf(&mut self, depth: u8) -> Result<(), ParseSnailFishError> {
+        let start = self.offset;
+        let mut val: Option<u32> = Some(0);
+        while let Some(digit) = self.bytes.get(self.offset).filter(|b| b.is_ascii_digit()) {
+            val = val
+                .and_then(|val| val.checked_mul(10))
+                .and_then(|val| val.checked_add((digit - b'0') as u32));
+            self.offset += 1;
         }
-        let mut open = 0;
-        let mut mid = 0;
-        for (i, c) in s.chars().enumerate() {
-            match c {
-                '[' => open += 1,
-                ']' => open -= 1,
-                ',' => {
-                    if open == 1 {
-                        mid = i;
-                        break;
-                    }
-                }
-                _ => (),
-            };
+        if self.offset == start {
+            return Err(ParseSnailFishError::InvalidLeaf { offset: start });
         }
+        let val = val
+            .and_then(|val| T::try_from(val).ok())
+            .ok_or(ParseSnailFishError::LeafOverflow { offset: start })?;
+        self.leaves.push(Leaf { val, depth });
+        return Ok(());
+    }
+}
 
-        let (left, right) = s.split_at(mid);
+impl<T: LeafValue> FromStr for SnailFishNumberOf<T> {
+    type Err = ParseSnailFishError;
 
-        let left = left.strip_prefix('[').unwrap();
-        let right = right.strip_prefix(',').unwrap().strip_suffix(']').unwrap();
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            bytes: s.as_bytes(),
+            offset: 0,
+            leaves: vec![],
+        };
+        parser.parse_element(0)?;
+        return match parser.bytes.get(parser.offset) {
+            None => Ok(SnailFishNumberOf {
+                leaves: parser.leaves,
+            }),
+            Some(b']') => Err(ParseSnailFishError::UnmatchedBracket {
+                offset: parser.offset,
+            }),
+            Some(_) => Err(ParseSnailFishError::InvalidLeaf {
+                offset: parser.offset,
+            }),
+        };
+    }
+}
 
-        return Ok(SnailFishNumber::new_node(
-            SnailFishNumber::from_str(left).unwrap(),
-            SnailFishNumber::from_str(right).unwrap(),
-        ));
+impl<T: LeafValue> Add for &SnailFishNumberOf<T> {
+    type Output = SnailFishNumberOf<T>;
+    fn add(self: Self, rhs: Self) -> Self::Output {
+        let mut leaves = Vec::with_capacity(self.leaves.len() + rhs.leaves.len());
+        for leaf in self.leaves.iter().chain(rhs.leaves.iter()) {
+            leaves.push(Leaf {
+                val: leaf.val,
+                depth: leaf.depth + 1,
+            });
+        }
+        let mut new = SnailFishNumberOf { leaves };
+        new._reduce();
+        return new;
     }
 }
 
-impl Add for SnailFishNumber {
+impl<T: LeafValue> Add for SnailFishNumberOf<T> {
     type Output = Self;
     fn add(self: Self, rhs: Self) -> Self::Output {
-        let mut new = SnailFishNumber::new_node(self, rhs);
-        while !matches!(new._explode(0), Explosion::NO) || !matches!(new._split(), Split::No) {}
+        let mut new = SnailFishNumberOf::new_node(self, rhs);
+        new._reduce();
         return new;
     }
 }
@@ -350,6 +617,100 @@ mod tests {
         assert_eq!(number.magnitude(), magnitude)
     }
 
+    #[rstest]
+    #[case("[1,2]")]
+    #[case("[[[[0,7],4],[[7,8],[6,0]]],[8,1]]")]
+    #[case("[[[[8,7],[7,7]],[[8,6],[7,7]]],[[[0,7],[6,6]],[8,7]]]")]
+    fn test_tree_view(#[case] input: &str) {
+        let number = SnailFishNumber::from_str(input).unwrap();
+        let tree = number.to_tree();
+        assert_eq!(tree.to_string(), input);
+        assert_eq!(tree.magnitude(), number.magnitude());
+        assert_eq!(SnailFishNumber::from(&tree), number);
+    }
+
+    #[rstest]
+    #[case("[1,2", ParseSnailFishError::UnclosedBracket { offset: 0 })]
+    #[case("[[1,2],[3,4]", ParseSnailFishError::UnclosedBracket { offset: 0 })]
+    #[case("[[1,2,3],4]", ParseSnailFishError::UnclosedBracket { offset: 1 })]
+    #[case("[1,2]]", ParseSnailFishError::UnmatchedBracket { offset: 5 })]
+    #[case("[1 2]", ParseSnailFishError::InvalidLeaf { offset: 2 })]
+    #[case("[[1,2][3,4]]", ParseSnailFishError::MissingComma { offset: 6 })]
+    #[case("[1,a]", ParseSnailFishError::InvalidLeaf { offset: 3 })]
+    #[case("[,2]", ParseSnailFishError::InvalidLeaf { offset: 1 })]
+    #[case("", ParseSnailFishError::InvalidLeaf { offset: 0 })]
+    #[case("[256,1]", ParseSnailFishError::LeafOverflow { offset: 1 })]
+    #[case("[[[[[[1,2],3],4],5],6],7]", ParseSnailFishError::TooDeep { offset: 5 })]
+    fn test_parse_errors(#[case] input: &str, #[case] expected: ParseSnailFishError) {
+        assert_eq!(SnailFishNumber::from_str(input), Err(expected))
+    }
+
+    #[test]
+    fn test_wide_leaves() {
+        let number = SnailFishNumberOf::<u16>::from_str("[300,[10,2]]").unwrap();
+        assert_eq!(number.to_string(), "[300,[10,2]]");
+        assert_eq!(number.magnitude(), 3 * 300 + 2 * (3 * 10 + 2 * 2));
+
+        let sum = &number + &SnailFishNumberOf::<u16>::from_str("[1,1]").unwrap();
+        assert!(sum
+            .leaves
+            .iter()
+            .all(|leaf| leaf.val < 10 && leaf.depth <= 4));
+        assert_eq!(
+            SnailFishNumber::from_str("[10,2]").unwrap().to_string(),
+            "[10,2]"
+        );
+    }
+
+    #[test]
+    fn test_explode_unreduced() {
+        let mut number = SnailFishNumber::from_str("[[[[[9,8],1],2],3],4]").unwrap();
+        number._reduce();
+        assert_eq!(number.to_string(), "[[[[0,9],2],3],4]");
+    }
+
+    #[test]
+    #[should_panic(expected = "snailfish leaf does not fit in the leaf type")]
+    fn test_explode_leaf_overflow() {
+        let lhs = SnailFishNumber::from_str("[[[[1,250],250],1],1]").unwrap();
+        let rhs = SnailFishNumber::from_str("[9,9]").unwrap();
+        let _ = &lhs + &rhs;
+    }
+
+    #[test]
+    fn test_add_traced() {
+        let lhs = SnailFishNumber::from_str("[[[[4,3],4],4],[7,[[8,4],9]]]").unwrap();
+        let rhs = SnailFishNumber::from_str("[1,1]").unwrap();
+        let steps = lhs.add_traced(&rhs);
+
+        let trace = steps
+            .iter()
+            .map(|step| step.to_string())
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(
+            trace,
+            "after addition: [[[[[4,3],4],4],[7,[[8,4],9]]],[1,1]]
+after explode:  [[[[0,7],4],[7,[[8,4],9]]],[1,1]]
+after explode:  [[[[0,7],4],[15,[0,13]]],[1,1]]
+after split:    [[[[0,7],4],[[7,8],[0,13]]],[1,1]]
+after split:    [[[[0,7],4],[[7,8],[0,[6,7]]]],[1,1]]
+after explode:  [[[[0,7],4],[[7,8],[6,0]]],[8,1]]"
+        );
+        assert_eq!(
+            steps[1].action,
+            ReductionAction::Explode {
+                index: 0,
+                pair: (4, 3)
+            }
+        );
+        assert_eq!(
+            steps[3].action,
+            ReductionAction::Split { index: 3, val: 15 }
+        );
+        assert_eq!(steps.last().unwrap().number, lhs + rhs);
+    }
+
     #[test]
     fn test_part_1() {
         let input = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
@@ -379,4 +740,53 @@ mod tests {
 [[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
         assert_eq!(aoc_18_part_2(input), 3993);
     }
+
+    #[test]
+    fn test_best_pairs() {
+        let input = "[[[0,[5,8]],[[1,7],[9,6]]],[[4,[1,2]],[[1,4],2]]]
+[[[5,[2,8]],4],[5,[[9,9],0]]]
+[6,[[[6,2],[5,6]],[[7,6],[4,7]]]]
+[[[6,[0,7]],[0,9]],[4,[9,[9,0]]]]
+[[[7,[6,4]],[3,[1,3]]],[[[5,5],1],9]]
+[[6,[[7,3],[3,2]]],[[[3,8],[5,7]],4]]
+[[[[5,4],[7,7]],8],[[8,3],8]]
+[[9,3],[[9,9],[6,[4,9]]]]
+[[2,[[7,7],7]],[[5,8],[[9,3],[0,2]]]]
+[[[[5,2],5],[8,[3,7]]],[[5,[7,5]],[4,4]]]";
+        let numbers = parse_input_str(input);
+        assert_eq!(
+            best_pair(&numbers),
+            Some(PairMagnitude {
+                magnitude: 3993,
+                lhs: 8,
+                rhs: 0
+            })
+        );
+
+        let mut expected = vec![];
+        for lhs in 0..numbers.len() {
+            for rhs in 0..numbers.len() {
+                if lhs != rhs {
+                    let magnitude = (&numbers[lhs] + &numbers[rhs]).magnitude();
+                    expected.push(PairMagnitude {
+                        magnitude,
+                        lhs,
+                        rhs,
+                    });
+                }
+            }
+        }
+        expected.sort_by(|a, b| b.magnitude.cmp(&a.magnitude).then(a.cmp(b)));
+        expected.truncate(5);
+        assert_eq!(best_pairs(&numbers, 5), expected);
+
+        assert_eq!(best_pair(&numbers[..1]), None);
+        assert_eq!(best_pairs(&numbers, 200).len(), 90);
+    }
+
+    #[test]
+    fn test_actual_input() {
+        let input = std::fs::read_to_string("src/input").unwrap();
+        assert_eq!(aoc_18_comp(&input), (3305, 4563));
+    }
 }
@@ -1 +1,31 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
 
+extern crate aoc_19;
+use aoc_19::*;
+
+fn speed_test_day_19(c: &mut Criterion) {
+    let input_str = read_file("../all_inputs/aoc_19_input.txt");
+    c.bench_function("day 19 total", |b| {
+        b.iter(|| aoc_19_comp(black_box(&input_str)))
+    });
+
+    let scanners = parse_file(&input_str);
+    c.bench_function("day 19 align scanners", |b| {
+        b.iter(|| align_scanners(black_box(&scanners)))
+    });
+
+    let synthetic = generate_scanners(&GeneratorConfig {
+        scanners: 80,
+        ..GeneratorConfig::default()
+    })
+    .unwrap();
+    let mut group = c.benchmark_group("day 19 synthetic");
+    group.sample_size(10);
+    group.bench_function("align 80 scanners", |b| {
+        b.iter(|| align_scanners(black_box(&synthetic.scanners)))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, speed_test_day_19);
+criterion_main!(benches);
@@ -1,5 +1,5 @@
-use itertools::Itertools;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufReader, Read};
 use std::str::FromStr;
@@ -15,36 +15,333 @@ pub fn read_file(file: &str) -> String {
 }
 
 pub fn aoc_19_comp(input_str: &str) -> (u16, u16) {
-    let mut input = parse_file(input_str);
-    let mut main_scanner = input.remove(0);
-    let mut moved_scanners = vec![main_scanner.clone()];
-    while !input.is_empty() {
-        let other = input.remove(0);
-        if let Ok(other_moved) = main_scanner.join(&other) {
-            moved_scanners.push(other_moved);
-        } else {
-            input.push(other);
-        }
-    }
+    let scanners = parse_file(input_str);
+    let poses = align_scanners(&scanners).unwrap();
+    let map = BeaconMap::new(&scanners, &poses);
 
     let mut max_distance = 0;
-    for scanner1 in &moved_scanners {
-        for scanner2 in &moved_scanners {
-            let distance = (scanner1.loc[0] - scanner2.loc[0]).abs() as u16
-                + (scanner1.loc[1] - scanner2.loc[1]).abs() as u16
-                + (scanner1.loc[2] - scanner2.loc[2]).abs() as u16;
+    for pose1 in &poses {
+        for pose2 in &poses {
+            let distance = (pose1.translation[0] - pose2.translation[0]).abs() as u16
+                + (pose1.translation[1] - pose2.translation[1]).abs() as u16
+                + (pose1.translation[2] - pose2.translation[2]).abs() as u16;
             if max_distance < distance {
                 max_distance = distance;
             }
         }
     }
 
-    return (main_scanner.beacons.len() as u16, max_distance);
+    return (map.beacons.len() as u16, max_distance);
+}
+
+/// The reconstructed map, all beacons and scanners in the frame of scanner 0.
+#[derive(PartialEq, Debug, Clone)]
+pub struct BeaconMap {
+    /// Sorted and without duplicates
+    pub beacons: Vec<Beacon>,
+    pub scanners: Vec<(u8, Pose)>,
+}
+
+impl BeaconMap {
+    /// `poses` as returned by `align_scanners` for `scanners`.
+    pub fn new(scanners: &[Scanner], poses: &[Pose]) -> BeaconMap {
+        let mut beacons = scanners
+            .iter()
+            .zip(poses)
+            .flat_map(|(scanner, pose)| scanner.beacons.iter().map(move |b| pose.apply(b)))
+            .collect::<Vec<Beacon>>();
+        beacons.sort();
+        beacons.dedup();
+        let scanners = scanners
+            .iter()
+            .zip(poses)
+            .map(|(scanner, pose)| (scanner.id, *pose))
+            .collect();
+        return BeaconMap { beacons, scanners };
+    }
+
+    /// One row per scanner and beacon: `kind,id,x,y,z,rotation`. The rotation is the index
+    /// into `ROTATIONS` and is left empty for beacons, as is the id.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("kind,id,x,y,z,rotation\n");
+        for (id, pose) in &self.scanners {
+            let [x, y, z] = pose.translation;
+            let rotation = rotation_id(&pose.rotation).unwrap();
+            csv.push_str(&format!("scanner,{},{},{},{},{}\n", id, x, y, z, rotation));
+        }
+        for beacon in &self.beacons {
+            let [x, y, z] = beacon.coords;
+            csv.push_str(&format!("beacon,,{},{},{},\n", x, y, z));
+        }
+        return csv;
+    }
+
+    /// The map as the report of a single scanner 0 that sees every beacon. The scanner poses
+    /// are only kept by `to_csv`.
+    pub fn to_report(&self) -> String {
+        return Scanner {
+            id: 0,
+            beacons: self.beacons.clone(),
+            loc: [0, 0, 0],
+        }
+        .to_string();
+    }
+}
+
+/// A row of a `BeaconMap` CSV that could not be read, `line` counts from 1.
+#[derive(PartialEq, Debug)]
+pub struct InvalidCsv {
+    pub line: usize,
+    pub content: String,
+}
+
+/// Reads the output of `BeaconMap::to_csv` back.
+impl FromStr for BeaconMap {
+    type Err = InvalidCsv;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.lines().next().is_none() {
+            return Err(InvalidCsv {
+                line: 1,
+                content: String::new(),
+            });
+        }
+        let mut beacons = vec![];
+        let mut scanners = vec![];
+        for (index, row) in s.lines().enumerate() {
+            let invalid = || InvalidCsv {
+                line: index + 1,
+                content: String::from(row),
+            };
+            if index == 0 {
+                if row != "kind,id,x,y,z,rotation" {
+                    return Err(invalid());
+                }
+                continue;
+            }
+            let fields = row.split(',').collect::<Vec<&str>>();
+            if fields.len() != 6 {
+                return Err(invalid());
+            }
+            let mut coords = [0; 3];
+            for (coord, field) in coords.iter_mut().zip(&fields[2..5]) {
+                *coord = field.parse::<i16>().map_err(|_| invalid())?;
+            }
+            match (fields[0], fields[1], fields[5]) {
+                ("scanner", id, rotation) => {
+                    let id = id.parse::<u8>().map_err(|_| invalid())?;
+                    let rotation = rotation
+                        .parse::<u16>()
+                        .ok()
+                        .filter(|rotation| (*rotation as usize) < ROTATIONS.len())
+                        .ok_or_else(invalid)?;
+                    scanners.push((id, Pose::from_rotation_id(rotation, coords)));
+                }
+                ("beacon", "", "") => beacons.push(Beacon { coords }),
+                _ => return Err(invalid()),
+            }
+        }
+        beacons.sort();
+        beacons.dedup();
+        return Ok(BeaconMap { beacons, scanners });
+    }
+}
+
+/// Row major rotation matrix acting on column vectors.
+pub type Rotation = [[i16; 3]; 3];
+
+/// The 24 proper rotations of the axes, reflections are excluded. The identity comes first.
+pub const ROTATIONS: [Rotation; 24] = [
+    [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+    [[1, 0, 0], [0, 0, 1], [0, -1, 0]],
+    [[1, 0, 0], [0, -1, 0], [0, 0, -1]],
+    [[1, 0, 0], [0, 0, -1], [0, 1, 0]],
+    [[0, 1, 0], [1, 0, 0], [0, 0, -1]],
+    [[0, 1, 0], [0, 0, 1], [1, 0, 0]],
+    [[0, 1, 0], [-1, 0, 0], [0, 0, 1]],
+    [[0, 1, 0], [0, 0, -1], [-1, 0, 0]],
+    [[0, 0, 1], [1, 0, 0], [0, 1, 0]],
+    [[0, 0, 1], [0, 1, 0], [-1, 0, 0]],
+    [[0, 0, 1], [-1, 0, 0], [0, -1, 0]],
+    [[0, 0, 1], [0, -1, 0], [1, 0, 0]],
+    [[-1, 0, 0], [0, 1, 0], [0, 0, -1]],
+    [[-1, 0, 0], [0, 0, 1], [0, 1, 0]],
+    [[-1, 0, 0], [0, -1, 0], [0, 0, 1]],
+    [[-1, 0, 0], [0, 0, -1], [0, -1, 0]],
+    [[0, -1, 0], [1, 0, 0], [0, 0, 1]],
+    [[0, -1, 0], [0, 0, 1], [-1, 0, 0]],
+    [[0, -1, 0], [-1, 0, 0], [0, 0, -1]],
+    [[0, -1, 0], [0, 0, -1], [1, 0, 0]],
+    [[0, 0, -1], [1, 0, 0], [0, -1, 0]],
+    [[0, 0, -1], [0, 1, 0], [1, 0, 0]],
+    [[0, 0, -1], [-1, 0, 0], [0, 1, 0]],
+    [[0, 0, -1], [0, -1, 0], [-1, 0, 0]],
+];
+
+fn rotate(rotation: &Rotation, coords: [i16; 3]) -> [i16; 3] {
+    let mut rotated = [0; 3];
+    for row in 0..3 {
+        rotated[row] = (0..3).map(|k| rotation[row][k] * coords[k]).sum();
+    }
+    return rotated;
+}
+
+fn multiply(lhs: &Rotation, rhs: &Rotation) -> Rotation {
+    let mut product = [[0; 3]; 3];
+    for row in 0..3 {
+        for col in 0..3 {
+            product[row][col] = (0..3).map(|k| lhs[row][k] * rhs[k][col]).sum();
+        }
+    }
+    return product;
+}
+
+fn transpose(rotation: &Rotation) -> Rotation {
+    let mut transposed = [[0; 3]; 3];
+    for row in 0..3 {
+        for col in 0..3 {
+            transposed[row][col] = rotation[col][row];
+        }
+    }
+    return transposed;
+}
+
+/// Index of `rotation` in `ROTATIONS`, None for reflections and other matrices.
+pub fn rotation_id(rotation: &Rotation) -> Option<u16> {
+    return ROTATIONS
+        .iter()
+        .position(|candidate| candidate == rotation)
+        .map(|id| id as u16);
+}
+
+/// The rotation that first applies `rhs` and then `lhs`.
+pub fn compose_rotations(lhs: u16, rhs: u16) -> u16 {
+    let product = multiply(&ROTATIONS[lhs as usize], &ROTATIONS[rhs as usize]);
+    return rotation_id(&product).unwrap();
+}
+
+pub fn invert_rotation(rot_id: u16) -> u16 {
+    // Rotation matrices are orthogonal, so the inverse is the transpose
+    return rotation_id(&transpose(&ROTATIONS[rot_id as usize])).unwrap();
+}
+
+/// Position and orientation of a scanner relative to scanner 0.
+/// A beacon `b` seen by the scanner is at `rotation * b + translation` for scanner 0.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct Pose {
+    pub rotation: Rotation,
+    pub translation: [i16; 3],
+}
+
+impl Pose {
+    pub fn identity() -> Pose {
+        return Pose {
+            rotation: [[1, 0, 0], [0, 1, 0], [0, 0, 1]],
+            translation: [0, 0, 0],
+        };
+    }
+
+    fn from_rotation_id(rot_id: u16, translation: [i16; 3]) -> Pose {
+        return Pose {
+            rotation: ROTATIONS[rot_id as usize],
+            translation,
+        };
+    }
+
+    pub fn apply(&self, beacon: &Beacon) -> Beacon {
+        return Beacon {
+            coords: rotate(&self.rotation, beacon.coords),
+        }
+        .apply_offset(self.translation);
+    }
+
+    /// The pose that first applies `other` and then `self`.
+    pub fn compose(&self, other: &Pose) -> Pose {
+        return Pose {
+            rotation: multiply(&self.rotation, &other.rotation),
+            translation: self
+                .apply(&Beacon {
+                    coords: other.translation,
+                })
+                .coords,
+        };
+    }
+
+    pub fn inverse(&self) -> Pose {
+        let rotation = transpose(&self.rotation);
+        let translation = rotate(&rotation, self.translation);
+        return Pose {
+            rotation,
+            translation: [-translation[0], -translation[1], -translation[2]],
+        };
+    }
+}
+
+#[derive(PartialEq, Debug)]
+pub enum AlignmentError {
+    /// Scanners that share no chain of overlaps with scanner 0
+    Disconnected { scanners: Vec<u8> },
+}
+
+/// Finds the pose of every scanner relative to the first one. Scanners are placed with a
+/// breadth first search over the pairwise overlaps, so every pair is compared at most once.
+pub fn align_scanners(scanners: &[Scanner]) -> Result<Vec<Pose>, AlignmentError> {
+    return align_scanners_with(scanners, &MatcherConfig::default());
+}
+
+pub fn align_scanners_with(
+    scanners: &[Scanner],
+    config: &MatcherConfig,
+) -> Result<Vec<Pose>, AlignmentError> {
+    if scanners.is_empty() {
+        return Ok(vec![]);
+    }
+    let distances = scanners
+        .iter()
+        .map(|scanner| find_distances(&scanner.beacons, config.fingerprint))
+        .collect::<Vec<_>>();
+
+    let mut poses: Vec<Option<Pose>> = vec![None; scanners.len()];
+    poses[0] = Some(Pose::identity());
+    let mut queue = VecDeque::from([0]);
+    while let Some(placed) = queue.pop_front() {
+        let placed_pose = poses[placed].unwrap();
+        for other in 0..scanners.len() {
+            if poses[other].is_some() {
+                continue;
+            }
+            let report = compare_scanners(
+                &scanners[placed],
+                &scanners[other],
+                &distances[placed],
+                &distances[other],
+                config,
+            );
+            if let Ok((rotation, offset)) = report.result {
+                let relative = Pose::from_rotation_id(rotation, offset);
+                poses[other] = Some(placed_pose.compose(&relative));
+                queue.push_back(other);
+            }
+        }
+    }
+
+    let disconnected = scanners
+        .iter()
+        .zip(&poses)
+        .filter(|(_, pose)| pose.is_none())
+        .map(|(scanner, _)| scanner.id)
+        .collect::<Vec<u8>>();
+    if !disconnected.is_empty() {
+        return Err(AlignmentError::Disconnected {
+            scanners: disconnected,
+        });
+    }
+    return Ok(poses.into_iter().map(|pose| pose.unwrap()).collect());
 }
 
 #[derive(PartialEq, PartialOrd, Ord, Debug, Eq, Hash, Clone, Copy)]
-struct Beacon {
-    coords: [i16; 3],
+pub struct Beacon {
+    pub coords: [i16; 3],
 }
 
 impl FromStr for Beacon {
@@ -63,31 +360,8 @@ impl FromStr for Beacon {
 
 impl Beacon {
     fn apply_rotation(self: &Self, rot_id: u16) -> Beacon {
-        let coords = [
-            self.coords[0],
-            self.coords[1],
-            self.coords[2],
-            -self.coords[0],
-            -self.coords[1],
-            -self.coords[2],
-        ];
-        let permutations = (0..6)
-            .permutations(3)
-            // Remove any that do not include all axis
-            .filter(|coord_idx| {
-                HashSet::<usize>::from_iter(coord_idx.iter().map(|x| x % 3)).len() == 3
-            })
-            .map(|coord_idx| {
-                [
-                    coords[coord_idx[0]],
-                    coords[coord_idx[1]],
-                    coords[coord_idx[2]],
-                ]
-            })
-            .collect::<Vec<[i16; 3]>>();
-
         return Beacon {
-            coords: permutations[rot_id as usize],
+            coords: rotate(&ROTATIONS[rot_id as usize], self.coords),
         };
     }
 
@@ -103,7 +377,7 @@ impl Beacon {
 }
 
 #[derive(PartialEq, Debug, Clone)]
-struct Scanner {
+pub struct Scanner {
     id: u8,
     beacons: Vec<Beacon>,
     loc: [i16; 3],
@@ -136,80 +410,222 @@ impl FromStr for Scanner {
     }
 }
 
-#[derive(Debug)]
-struct UnJoinable {
-    reason: String,
+/// Writes the report in the puzzle format, so it parses back with `Scanner::from_str`.
+impl Display for Scanner {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "--- scanner {} ---", self.id)?;
+        for beacon in &self.beacons {
+            let [x, y, z] = beacon.coords;
+            writeln!(f, "{},{},{}", x, y, z)?;
+        }
+        return Ok(());
+    }
 }
 
-fn calculate_fingerprint(lhs: &Beacon, rhs: &Beacon) -> (u16, i16) {
-    return (
-        (lhs.coords[0] - rhs.coords[0]).abs() as u16
-            + (lhs.coords[1] - rhs.coords[1]).abs() as u16
-            + (lhs.coords[2] - rhs.coords[2]).abs() as u16,
-        (lhs.coords[0] - rhs.coords[0]).abs().max(
-            (lhs.coords[1] - rhs.coords[1])
-                .abs()
-                .max((lhs.coords[2] - rhs.coords[2]).abs()),
-        ),
-    );
+type FingerprintKey = [i32; 3];
+type FingerprintMap<'a> = HashMap<&'a Beacon, HashSet<FingerprintKey>>;
+
+/// Rotation invariant summary of the vector between two beacons.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Fingerprint {
+    /// Manhattan and Chebyshev distance, different vectors collide easily
+    ManhattanChebyshev,
+    SquaredEuclidean,
+    /// Absolute deltas per axis in ascending order
+    SortedAbsDeltas,
+}
+
+impl Fingerprint {
+    fn calculate(&self, lhs: &Beacon, rhs: &Beacon) -> FingerprintKey {
+        let deltas = [0, 1, 2].map(|i| (lhs.coords[i] as i32 - rhs.coords[i] as i32).abs());
+        return match self {
+            Fingerprint::ManhattanChebyshev => {
+                [deltas.iter().sum(), *deltas.iter().max().unwrap(), 0]
+            }
+            Fingerprint::SquaredEuclidean => [deltas.iter().map(|d| d * d).sum(), 0, 0],
+            Fingerprint::SortedAbsDeltas => {
+                let mut sorted = deltas;
+                sorted.sort();
+                sorted
+            }
+        };
+    }
+}
+
+/// What to do when a beacon shares enough fingerprints with several beacons of the other scanner.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum Ambiguity {
+    /// Pair it with the first candidate
+    FirstMatch,
+    /// Pair it with the candidate sharing the most fingerprints, the first one on ties
+    BestMatch,
+    /// Leave the beacon unmatched
+    Reject,
+}
+
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct MatcherConfig {
+    /// Number of beacons two scanners need to have in common to be joined
+    pub required_overlap: usize,
+    pub fingerprint: Fingerprint,
+    pub ambiguity: Ambiguity,
 }
 
-fn find_distances(beacons: &Vec<Beacon>) -> HashMap<&Beacon, HashSet<(u16, i16)>> {
+impl Default for MatcherConfig {
+    fn default() -> Self {
+        return MatcherConfig {
+            required_overlap: 12,
+            fingerprint: Fingerprint::SortedAbsDeltas,
+            ambiguity: Ambiguity::BestMatch,
+        };
+    }
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum UnJoinable {
+    NotEnoughMatches {
+        matched: usize,
+        required: usize,
+    },
+    /// The matched beacons do not agree on a single rotation and offset
+    NoConsistentTransform {
+        matched: usize,
+    },
+}
+
+/// Outcome of matching the beacons of scanner `rhs` against scanner `lhs`.
+#[derive(PartialEq, Debug, Clone)]
+pub struct JoinReport {
+    pub lhs: u8,
+    pub rhs: u8,
+    /// Beacons of `lhs` that were paired with a beacon of `rhs`
+    pub matched: usize,
+    /// Beacons of `lhs` with more than one candidate in `rhs`
+    pub ambiguous: usize,
+    /// Most fingerprints shared by any pair of beacons, including the zero self distance
+    pub best_shared: usize,
+    /// Rotation and offset that move `rhs` into the frame of `lhs`
+    pub result: Result<(u16, [i16; 3]), UnJoinable>,
+}
+
+impl Display for JoinReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "scanner {} -> scanner {}: ", self.lhs, self.rhs)?;
+        match &self.result {
+            Ok((rotation, offset)) => write!(
+                f,
+                "joined with rotation {} at {},{},{}",
+                rotation, offset[0], offset[1], offset[2]
+            )?,
+            Err(UnJoinable::NotEnoughMatches { matched, required }) => write!(
+                f,
+                "not joined, {} of the required {} beacons matched",
+                matched, required
+            )?,
+            Err(UnJoinable::NoConsistentTransform { matched }) => write!(
+                f,
+                "not joined, the {} matched beacons disagree on the transform",
+                matched
+            )?,
+        }
+        write!(
+            f,
+            " ({} ambiguous, best pair shared {} fingerprints)",
+            self.ambiguous, self.best_shared
+        )
+    }
+}
+
+fn find_distances(beacons: &Vec<Beacon>, fingerprint: Fingerprint) -> FingerprintMap<'_> {
     let mut all_distances = HashMap::new();
     for beacon1 in beacons {
         let mut distances = HashSet::new();
-        let mut vec_dist = vec![];
         for beacon2 in beacons {
-            distances.insert(calculate_fingerprint(beacon1, beacon2));
-            vec_dist.push(calculate_fingerprint(beacon1, beacon2));
+            distances.insert(fingerprint.calculate(beacon1, beacon2));
         }
         all_distances.insert(beacon1, distances);
     }
     return all_distances;
 }
 
+#[cfg(test)]
 fn find_matching_beacons(
     lhs: &Vec<Beacon>,
     rhs: &Vec<Beacon>,
     threshold: usize,
 ) -> HashMap<Beacon, Beacon> {
-    let lhs_distances = find_distances(&lhs);
-    let rhs_distances = find_distances(&rhs);
+    let config = MatcherConfig {
+        required_overlap: threshold,
+        ambiguity: Ambiguity::FirstMatch,
+        ..MatcherConfig::default()
+    };
+    let lhs_distances = find_distances(lhs, config.fingerprint);
+    let rhs_distances = find_distances(rhs, config.fingerprint);
+    return match_distances(lhs, rhs, &lhs_distances, &rhs_distances, &config).matching;
+}
 
+struct Matching {
+    matching: HashMap<Beacon, Beacon>,
+    ambiguous: usize,
+    best_shared: usize,
+}
+
+fn match_distances(
+    lhs: &Vec<Beacon>,
+    rhs: &Vec<Beacon>,
+    lhs_distances: &FingerprintMap,
+    rhs_distances: &FingerprintMap,
+    config: &MatcherConfig,
+) -> Matching {
     let mut matching = HashMap::<Beacon, Beacon>::new();
+    let mut ambiguous = 0;
+    let mut best_shared = 0;
     for beacon1 in lhs {
+        let mut candidates = vec![];
         for beacon2 in rhs {
-            let overlapping = lhs_distances
+            let shared = lhs_distances
                 .get(beacon1)
                 .unwrap()
                 .intersection(rhs_distances.get(beacon2).unwrap())
-                .collect::<HashSet<&(u16, i16)>>();
-            if overlapping.len() >= threshold {
-                matching.insert(beacon1.clone(), beacon2.clone());
-                break;
+                .count();
+            best_shared = best_shared.max(shared);
+            if shared >= config.required_overlap {
+                candidates.push((shared, beacon2));
             }
         }
+        if candidates.len() > 1 {
+            ambiguous += 1;
+        }
+        let chosen = match config.ambiguity {
+            Ambiguity::FirstMatch => candidates.first(),
+            Ambiguity::BestMatch => candidates.iter().reduce(|best, candidate| {
+                if candidate.0 > best.0 {
+                    candidate
+                } else {
+                    best
+                }
+            }),
+            Ambiguity::Reject if candidates.len() == 1 => candidates.first(),
+            Ambiguity::Reject => None,
+        };
+        if let Some((_, beacon2)) = chosen {
+            matching.insert(*beacon1, **beacon2);
+        }
     }
-    return matching;
+    return Matching {
+        matching,
+        ambiguous,
+        best_shared,
+    };
 }
 
 fn find_rotation(matching_beacons: &HashMap<Beacon, Beacon>) -> Option<u16> {
-    for rotation in 0..48 {
-        let mut offset = None;
-        let mut correct_flag = true;
-        for (beacon_self, beacon_other) in matching_beacons {
-            let dist = calculate_fingerprint(beacon_self, &beacon_other.apply_rotation(rotation));
-            match offset {
-                None => offset = Some(dist),
-                Some(offset) => {
-                    if offset != dist {
-                        correct_flag = false;
-                        break;
-                    }
-                }
-            }
-        }
-        if correct_flag {
+    for rotation in 0..ROTATIONS.len() as u16 {
+        let offset = find_offset(matching_beacons, rotation);
+        let consistent = matching_beacons.iter().all(|(beacon_self, beacon_other)| {
+            beacon_other.apply_rotation(rotation).apply_offset(offset) == *beacon_self
+        });
+        if consistent {
             return Some(rotation);
         }
     }
@@ -226,23 +642,77 @@ fn find_offset(matching: &HashMap<Beacon, Beacon>, rotation: u16) -> [i16; 3] {
     ];
 }
 
+/// Rotation and offset that map the matched beacons of `rhs` onto those of `lhs`.
+fn find_transform(
+    matching_beacons: &HashMap<Beacon, Beacon>,
+    required_overlap: usize,
+) -> Result<(u16, [i16; 3]), UnJoinable> {
+    if matching_beacons.len() < required_overlap.max(1) {
+        return Err(UnJoinable::NotEnoughMatches {
+            matched: matching_beacons.len(),
+            required: required_overlap,
+        });
+    }
+
+    return match find_rotation(&matching_beacons) {
+        Some(rotation) => Ok((rotation, find_offset(&matching_beacons, rotation))),
+        None => Err(UnJoinable::NoConsistentTransform {
+            matched: matching_beacons.len(),
+        }),
+    };
+}
+
+fn compare_scanners(
+    lhs: &Scanner,
+    rhs: &Scanner,
+    lhs_distances: &FingerprintMap,
+    rhs_distances: &FingerprintMap,
+    config: &MatcherConfig,
+) -> JoinReport {
+    let matching = match_distances(
+        &lhs.beacons,
+        &rhs.beacons,
+        lhs_distances,
+        rhs_distances,
+        config,
+    );
+    return JoinReport {
+        lhs: lhs.id,
+        rhs: rhs.id,
+        matched: matching.matching.len(),
+        ambiguous: matching.ambiguous,
+        best_shared: matching.best_shared,
+        result: find_transform(&matching.matching, config.required_overlap),
+    };
+}
+
+/// Explains whether and how `rhs` can be moved into the frame of `lhs`.
+pub fn diagnose_join(lhs: &Scanner, rhs: &Scanner, config: &MatcherConfig) -> JoinReport {
+    return compare_scanners(
+        lhs,
+        rhs,
+        &find_distances(&lhs.beacons, config.fingerprint),
+        &find_distances(&rhs.beacons, config.fingerprint),
+        config,
+    );
+}
+
 impl Scanner {
-    fn join(self: &mut Self, other: &Self) -> Result<Self, UnJoinable> {
-        let matching_beacons = find_matching_beacons(&self.beacons, &other.beacons, 12);
-        if matching_beacons.len() < 11 {
-            return Err(UnJoinable {
-                reason: String::from("Not enough matches found"),
-            });
-        }
+    /// Adds the beacons of `other` to this scanner, returns `other` moved into this frame.
+    pub fn join(self: &mut Self, other: &Self) -> Result<Self, JoinReport> {
+        return self.join_with(other, &MatcherConfig::default());
+    }
 
-        let rotation = find_rotation(&matching_beacons);
-        if rotation.is_none() {
-            return Err(UnJoinable {
-                reason: String::from("No rotation found"),
-            });
-        }
-        let rotation = rotation.unwrap();
-        let offset = find_offset(&matching_beacons, rotation);
+    pub fn join_with(
+        self: &mut Self,
+        other: &Self,
+        config: &MatcherConfig,
+    ) -> Result<Self, JoinReport> {
+        let report = diagnose_join(self, other, config);
+        let (rotation, offset) = match report.result {
+            Ok(transform) => transform,
+            Err(_) => return Err(report),
+        };
 
         let other = other.apply_rotation(rotation);
         let other = other.apply_offset(offset);
@@ -282,13 +752,223 @@ impl Scanner {
     }
 }
 
-fn parse_file(input_str: &str) -> Vec<Scanner> {
+pub fn parse_file(input_str: &str) -> Vec<Scanner> {
     return input_str
         .split("\n\n")
         .map(|scanner_str| Scanner::from_str(scanner_str).unwrap())
         .collect();
 }
 
+/// Settings for `generate_scanners`, the defaults resemble the puzzle input.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub struct GeneratorConfig {
+    pub scanners: usize,
+    /// Scanners see beacons at most this far away on each axis
+    pub detection_range: i16,
+    /// Every scanner after the first shares at least this many beacons with an earlier one
+    pub overlap: usize,
+    /// Minimum number of beacons in the report of each scanner
+    pub beacons_per_scanner: usize,
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    fn default() -> Self {
+        return GeneratorConfig {
+            scanners: 30,
+            detection_range: 1000,
+            overlap: 12,
+            beacons_per_scanner: 26,
+            seed: 19,
+        };
+    }
+}
+
+/// Why `generate_scanners` refused a config.
+#[derive(PartialEq, Debug, Clone, Copy)]
+pub enum InvalidConfig {
+    /// Scanner ids are a single byte
+    TooManyScanners { scanners: usize },
+    /// The range must be positive and keep every coordinate inside `i16`
+    DetectionRange { range: i16 },
+    /// Two overlapping scanners may only share a cube of side `detection_range + 1`
+    OverlapTooLarge { overlap: usize, capacity: u64 },
+    /// More beacons than a single detection cube holds
+    TooManyBeacons { beacons: usize, capacity: u64 },
+}
+
+/// Scanner reports together with the poses they were generated from.
+#[derive(PartialEq, Debug, Clone)]
+pub struct SyntheticReport {
+    pub scanners: Vec<Scanner>,
+    /// Ground truth, in the same form as returned by `align_scanners`
+    pub poses: Vec<Pose>,
+    /// Every beacon in the frame of scanner 0, sorted
+    pub beacons: Vec<Beacon>,
+}
+
+/// Xorshift generator, keeps the synthetic reports reproducible from a seed.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        return XorShift((seed ^ 0x9E37_79B9_7F4A_7C15).max(1));
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        return self.0;
+    }
+
+    /// Uniform in `lo..=hi`, which must not be empty.
+    fn range(&mut self, lo: i16, hi: i16) -> i16 {
+        assert!(lo <= hi, "empty range {}..={}", lo, hi);
+        let width = (hi as i64 - lo as i64 + 1) as u64;
+        return (lo as i64 + (self.next() % width) as i64) as i16;
+    }
+}
+
+/// Keeps synthetic maps well inside the `i16` coordinates.
+const GENERATOR_EXTENT: i16 = 10_000;
+
+/// Scanners stay within `GENERATOR_EXTENT` and beacons within the detection range of one,
+/// so coordinates and their differences stay below `3 * GENERATOR_EXTENT`.
+const MAX_DETECTION_RANGE: i16 = GENERATOR_EXTENT;
+
+impl GeneratorConfig {
+    fn validate(self: &Self) -> Result<(), InvalidConfig> {
+        let range = self.detection_range;
+        if self.scanners > u8::MAX as usize + 1 {
+            return Err(InvalidConfig::TooManyScanners {
+                scanners: self.scanners,
+            });
+        }
+        if !(1..=MAX_DETECTION_RANGE).contains(&range) {
+            return Err(InvalidConfig::DetectionRange { range });
+        }
+        // Neighbours are at most `range` apart on each axis
+        let shared = (range as u64 + 1).pow(3);
+        if self.overlap as u64 > shared {
+            return Err(InvalidConfig::OverlapTooLarge {
+                overlap: self.overlap,
+                capacity: shared,
+            });
+        }
+        let cube = (2 * range as u64 + 1).pow(3);
+        if self.beacons_per_scanner as u64 > cube {
+            return Err(InvalidConfig::TooManyBeacons {
+                beacons: self.beacons_per_scanner,
+                capacity: cube,
+            });
+        }
+        return Ok(());
+    }
+}
+
+fn random_beacon(rng: &mut XorShift, lo: [i16; 3], hi: [i16; 3]) -> Beacon {
+    return Beacon {
+        coords: [0, 1, 2].map(|i| rng.range(lo[i], hi[i])),
+    };
+}
+
+fn in_range(beacon: &Beacon, position: [i16; 3], range: i16) -> bool {
+    return (0..3).all(|i| (beacon.coords[i] - position[i]).abs() <= range);
+}
+
+/// Places random beacons and scanners, every scanner overlapping with an earlier one, and
+/// writes the report each scanner would produce.
+pub fn generate_scanners(config: &GeneratorConfig) -> Result<SyntheticReport, InvalidConfig> {
+    config.validate()?;
+    let mut rng = XorShift::new(config.seed);
+    let range = config.detection_range;
+    let mut beacons = HashSet::<Beacon>::new();
+    let mut positions: Vec<[i16; 3]> = vec![];
+
+    for id in 0..config.scanners {
+        // Each scanner is placed within detection range of an earlier one, its parent
+        let (position, parent) = match id {
+            0 => ([0, 0, 0], None),
+            _ => loop {
+                let parent = positions[rng.next() as usize % positions.len()];
+                let candidate = parent.map(|c| c + rng.range(-range, range));
+                if candidate.iter().all(|c| c.abs() <= GENERATOR_EXTENT) {
+                    break (candidate, Some(parent));
+                }
+            },
+        };
+        let lo = position.map(|c| c - range);
+        let hi = position.map(|c| c + range);
+
+        // Top up the overlap with the parent, their detection cubes always intersect
+        if let Some(parent) = parent {
+            let shared_lo = [0, 1, 2].map(|i| lo[i].max(parent[i] - range));
+            let shared_hi = [0, 1, 2].map(|i| hi[i].min(parent[i] + range));
+            while beacons
+                .iter()
+                .filter(|b| in_range(b, position, range) && in_range(b, parent, range))
+                .count()
+                < config.overlap
+            {
+                beacons.insert(random_beacon(&mut rng, shared_lo, shared_hi));
+            }
+        }
+        while beacons
+            .iter()
+            .filter(|b| in_range(b, position, range))
+            .count()
+            < config.beacons_per_scanner
+        {
+            beacons.insert(random_beacon(&mut rng, lo, hi));
+        }
+        positions.push(position);
+    }
+
+    let poses = positions
+        .iter()
+        .enumerate()
+        .map(|(id, position)| match id {
+            0 => Pose::identity(),
+            _ => Pose::from_rotation_id(rng.range(0, ROTATIONS.len() as i16 - 1) as u16, *position),
+        })
+        .collect::<Vec<Pose>>();
+
+    let mut beacons = beacons.into_iter().collect::<Vec<Beacon>>();
+    beacons.sort();
+    let scanners = poses
+        .iter()
+        .enumerate()
+        .map(|(id, pose)| {
+            let to_local = pose.inverse();
+            return Scanner {
+                id: id as u8,
+                beacons: beacons
+                    .iter()
+                    .filter(|b| in_range(b, pose.translation, range))
+                    .map(|b| to_local.apply(b))
+                    .collect(),
+                loc: [0, 0, 0],
+            };
+        })
+        .collect();
+
+    return Ok(SyntheticReport {
+        scanners,
+        poses,
+        beacons,
+    });
+}
+
+/// Inverse of `parse_file`.
+pub fn write_file(scanners: &[Scanner]) -> String {
+    return scanners
+        .iter()
+        .map(|scanner| scanner.to_string())
+        .collect::<Vec<String>>()
+        .join("\n");
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -407,7 +1087,7 @@ mod tests {
             ])
     )]
     fn test_find_rotation(#[case] mappings: HashMap<Beacon, Beacon>) {
-        assert_eq!(find_rotation(&mappings), Some(25));
+        assert_eq!(find_rotation(&mappings), Some(12));
     }
 
     #[rstest]
@@ -427,18 +1107,18 @@ mod tests {
     }
 
     #[rstest]
-    #[case(Beacon {coords: [-618,-824,-621]}, Beacon {coords: [686,422,578]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [-537,-823,-458]}, Beacon {coords: [605,423,415]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [-447,-329,318]}, Beacon {coords: [515,917,-361]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [404,-588,-901]}, Beacon {coords: [-336,658,858]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [544,-627,-890]}, Beacon {coords: [-476,619,847]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [528,-643,409]}, Beacon {coords: [-460,603,-452]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [-661,-816,-575]}, Beacon {coords: [729,430,532]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [390,-675,-793]}, Beacon {coords: [-322,571,750]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [423,-701,434]}, Beacon {coords: [-355,545,-477]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [-345,-311,381]}, Beacon {coords: [413,935,-424]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [459,-707,401]}, Beacon {coords: [-391,539,-444]},25, [68, -1246, -43])]
-    #[case(Beacon {coords: [-485,-357,347]}, Beacon {coords: [553,889,-390]},25, [68, -1246, -43])]
+    #[case(Beacon {coords: [-618,-824,-621]}, Beacon {coords: [686,422,578]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [-537,-823,-458]}, Beacon {coords: [605,423,415]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [-447,-329,318]}, Beacon {coords: [515,917,-361]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [404,-588,-901]}, Beacon {coords: [-336,658,858]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [544,-627,-890]}, Beacon {coords: [-476,619,847]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [528,-643,409]}, Beacon {coords: [-460,603,-452]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [-661,-816,-575]}, Beacon {coords: [729,430,532]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [390,-675,-793]}, Beacon {coords: [-322,571,750]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [423,-701,434]}, Beacon {coords: [-355,545,-477]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [-345,-311,381]}, Beacon {coords: [413,935,-424]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [459,-707,401]}, Beacon {coords: [-391,539,-444]},12, [68, -1246, -43])]
+    #[case(Beacon {coords: [-485,-357,347]}, Beacon {coords: [553,889,-390]},12, [68, -1246, -43])]
     fn test_apply_rotation_and_offset(
         #[case] main: Beacon,
         #[case] secondary: Beacon,
@@ -451,7 +1131,7 @@ mod tests {
     #[test]
     fn test_apply_rotation() {
         let mut rotations = vec![];
-        for i in 0..48 {
+        for i in 0..24 {
             rotations.push(Beacon { coords: [1, 2, 3] }.apply_rotation(i));
         }
 
@@ -461,6 +1141,34 @@ mod tests {
         )
     }
 
+    #[test]
+    fn test_rotation_group() {
+        assert_eq!(ROTATIONS[0], Pose::identity().rotation);
+        for lhs in 0..24 {
+            assert_eq!(compose_rotations(lhs, invert_rotation(lhs)), 0);
+            assert_eq!(compose_rotations(invert_rotation(lhs), lhs), 0);
+            let products = (0..24)
+                .map(|rhs| compose_rotations(lhs, rhs))
+                .collect::<HashSet<u16>>();
+            assert_eq!(products.len(), 24);
+        }
+        let reflection = [[-1, 0, 0], [0, 1, 0], [0, 0, 1]];
+        assert_eq!(rotation_id(&reflection), None);
+    }
+
+    #[test]
+    fn test_reflection_does_not_join() {
+        let scanners = parse_file(&read_file("src/example_input"));
+        let mut mirrored = scanners[1].clone();
+        for beacon in mirrored.beacons.iter_mut() {
+            beacon.coords[0] = -beacon.coords[0];
+        }
+        let mut lhs = scanners[0].clone();
+        assert!(lhs.join(&scanners[1]).is_ok());
+        let mut lhs = scanners[0].clone();
+        assert!(lhs.join(&mirrored).is_err());
+    }
+
     #[rstest]
     #[case("src/example_input")]
     fn test_part_1_partially(#[case] input_file: &str) {
@@ -563,6 +1271,251 @@ mod tests {
         assert_eq!(beacon2.unwrap().loc, [-92, -2380, -20]);
     }
 
+    #[test]
+    fn test_align_scanners() {
+        let scanners = parse_file(&read_file("src/example_input"));
+        let poses = align_scanners(&scanners).unwrap();
+        let locations = poses
+            .iter()
+            .map(|pose| pose.translation)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            locations,
+            vec![
+                [0, 0, 0],
+                [68, -1246, -43],
+                [1105, -1205, 1229],
+                [-92, -2380, -20],
+                [-20, -1133, 1061]
+            ]
+        );
+        for pose in &poses {
+            let identity = pose.compose(&pose.inverse());
+            assert_eq!(identity, Pose::identity());
+            assert!(rotation_id(&pose.rotation).is_some());
+        }
+    }
+
+    #[test]
+    fn test_align_scanners_disconnected() {
+        let mut input_str = read_file("src/example_input").trim_end().to_string();
+        input_str.push_str("\n\n--- scanner 5 ---\n1,2,3\n-4,5,6\n7,-8,9");
+        let scanners = parse_file(&input_str);
+        assert_eq!(
+            align_scanners(&scanners),
+            Err(AlignmentError::Disconnected { scanners: vec![5] })
+        );
+    }
+
+    #[rstest]
+    #[case(Fingerprint::ManhattanChebyshev, Ambiguity::FirstMatch)]
+    #[case(Fingerprint::SquaredEuclidean, Ambiguity::BestMatch)]
+    #[case(Fingerprint::SortedAbsDeltas, Ambiguity::Reject)]
+    fn test_align_scanners_with(#[case] fingerprint: Fingerprint, #[case] ambiguity: Ambiguity) {
+        let scanners = parse_file(&read_file("src/example_input"));
+        let config = MatcherConfig {
+            fingerprint,
+            ambiguity,
+            ..MatcherConfig::default()
+        };
+        let poses = align_scanners_with(&scanners, &config).unwrap();
+        assert_eq!(poses, align_scanners(&scanners).unwrap());
+    }
+
+    #[test]
+    fn test_diagnose_join() {
+        let scanners = parse_file(&read_file("src/example_input"));
+        let config = MatcherConfig::default();
+
+        let report = diagnose_join(&scanners[0], &scanners[1], &config);
+        assert_eq!(report.matched, 12);
+        assert_eq!(report.ambiguous, 0);
+        assert_eq!(report.result, Ok((12, [68, -1246, -43])));
+        assert_eq!(
+            report.to_string(),
+            "scanner 0 -> scanner 1: joined with rotation 12 at 68,-1246,-43 (0 ambiguous, best pair shared 12 fingerprints)"
+        );
+
+        let report = diagnose_join(&scanners[0], &scanners[2], &config);
+        assert!(report.best_shared < 12);
+        assert_eq!(
+            report.result,
+            Err(UnJoinable::NotEnoughMatches {
+                matched: 0,
+                required: 12
+            })
+        );
+
+        let strict = MatcherConfig {
+            required_overlap: 13,
+            ..config
+        };
+        let report = diagnose_join(&scanners[0], &scanners[1], &strict);
+        assert_eq!(report.matched, 0);
+        assert!(scanners[0]
+            .clone()
+            .join_with(&scanners[1], &strict)
+            .is_err());
+    }
+
+    #[test]
+    fn test_write_file_round_trip() {
+        let input_str = read_file("src/example_input");
+        let scanners = parse_file(&input_str);
+        assert_eq!(write_file(&scanners), input_str);
+        assert_eq!(parse_file(&write_file(&scanners)), scanners);
+    }
+
+    #[test]
+    fn test_beacon_map_export() {
+        let scanners = parse_file(&read_file("src/example_input"));
+        let poses = align_scanners(&scanners).unwrap();
+        let map = BeaconMap::new(&scanners, &poses);
+        assert_eq!(map.beacons.len(), 79);
+
+        let reparsed = parse_file(&map.to_report());
+        assert_eq!(reparsed.len(), 1);
+        assert_eq!(reparsed[0].beacons, map.beacons);
+
+        // Every original report joins the exported map at its own pose
+        for (scanner, pose) in scanners.iter().zip(&poses) {
+            let report = diagnose_join(&reparsed[0], scanner, &MatcherConfig::default());
+            assert_eq!(
+                report
+                    .result
+                    .map(|(rot, offset)| Pose::from_rotation_id(rot, offset)),
+                Ok(*pose)
+            );
+        }
+
+        let csv = map.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("kind,id,x,y,z,rotation"));
+        assert_eq!(lines.next(), Some("scanner,0,0,0,0,0"));
+        assert_eq!(lines.next(), Some("scanner,1,68,-1246,-43,12"));
+        assert_eq!(csv.lines().filter(|l| l.starts_with("beacon,")).count(), 79);
+        assert_eq!(csv.parse::<BeaconMap>(), Ok(map));
+    }
+
+    #[rstest]
+    #[case("", 1)]
+    #[case("kind,id,x,y,z", 1)]
+    #[case("kind,id,x,y,z,rotation\nscanner,0,0,0,0,24", 2)]
+    #[case("kind,id,x,y,z,rotation\nscanner,,0,0,0,0", 2)]
+    #[case("kind,id,x,y,z,rotation\nbeacon,,1,2,3,\nbeacon,1,2,3,4,", 3)]
+    #[case("kind,id,x,y,z,rotation\nbeacon,,1,2,", 2)]
+    #[case("kind,id,x,y,z,rotation\nprobe,,1,2,3,", 2)]
+    fn test_beacon_map_invalid_csv(#[case] csv: &str, #[case] line: usize) {
+        assert_eq!(csv.parse::<BeaconMap>().unwrap_err().line, line);
+    }
+
+    #[rstest]
+    #[case(GeneratorConfig::default())]
+    #[case(GeneratorConfig { scanners: 8, overlap: 15, seed: 7, ..GeneratorConfig::default() })]
+    fn test_generate_scanners(#[case] config: GeneratorConfig) {
+        let synthetic = generate_scanners(&config).unwrap();
+        assert_eq!(synthetic.scanners.len(), config.scanners);
+        for scanner in &synthetic.scanners {
+            assert!(scanner.beacons.len() >= config.beacons_per_scanner);
+        }
+
+        let scanners = parse_file(&write_file(&synthetic.scanners));
+        let poses = align_scanners(&scanners).unwrap();
+        assert_eq!(poses, synthetic.poses);
+        assert_eq!(BeaconMap::new(&scanners, &poses).beacons, synthetic.beacons);
+    }
+
+    #[test]
+    fn test_generate_scanners_seeds() {
+        for seed in 0..100 {
+            let config = GeneratorConfig {
+                scanners: 12,
+                seed,
+                ..GeneratorConfig::default()
+            };
+            let synthetic = generate_scanners(&config).unwrap();
+            let positions = synthetic
+                .poses
+                .iter()
+                .map(|pose| pose.translation)
+                .collect::<Vec<[i16; 3]>>();
+            for (id, position) in positions.iter().enumerate().skip(1) {
+                let best_shared = positions[..id]
+                    .iter()
+                    .map(|other| {
+                        synthetic
+                            .beacons
+                            .iter()
+                            .filter(|b| {
+                                in_range(b, *position, config.detection_range)
+                                    && in_range(b, *other, config.detection_range)
+                            })
+                            .count()
+                    })
+                    .max()
+                    .unwrap();
+                assert!(
+                    best_shared >= config.overlap,
+                    "seed {} scanner {}",
+                    seed,
+                    id
+                );
+            }
+        }
+        assert!(generate_scanners(&GeneratorConfig {
+            seed: 74,
+            ..GeneratorConfig::default()
+        })
+        .is_ok());
+    }
+
+    #[rstest]
+    #[case(GeneratorConfig { scanners: 257, ..GeneratorConfig::default() },
+        InvalidConfig::TooManyScanners { scanners: 257 })]
+    #[case(GeneratorConfig { detection_range: 0, ..GeneratorConfig::default() },
+        InvalidConfig::DetectionRange { range: 0 })]
+    #[case(GeneratorConfig { detection_range: 20_000, ..GeneratorConfig::default() },
+        InvalidConfig::DetectionRange { range: 20_000 })]
+    #[case(GeneratorConfig { detection_range: 1, overlap: 9, ..GeneratorConfig::default() },
+        InvalidConfig::OverlapTooLarge { overlap: 9, capacity: 8 })]
+    #[case(GeneratorConfig { detection_range: 1, overlap: 8, beacons_per_scanner: 28, ..GeneratorConfig::default() },
+        InvalidConfig::TooManyBeacons { beacons: 28, capacity: 27 })]
+    fn test_generate_scanners_invalid(
+        #[case] config: GeneratorConfig,
+        #[case] expected: InvalidConfig,
+    ) {
+        assert_eq!(generate_scanners(&config), Err(expected));
+    }
+
+    #[test]
+    fn test_generate_scanners_limits() {
+        // The largest accepted configs still terminate with distinct ids
+        let synthetic = generate_scanners(&GeneratorConfig {
+            scanners: 256,
+            detection_range: MAX_DETECTION_RANGE,
+            overlap: 1,
+            beacons_per_scanner: 1,
+            seed: 3,
+        })
+        .unwrap();
+        let ids = synthetic
+            .scanners
+            .iter()
+            .map(|s| s.id)
+            .collect::<HashSet<u8>>();
+        assert_eq!(ids.len(), 256);
+
+        let synthetic = generate_scanners(&GeneratorConfig {
+            scanners: 3,
+            detection_range: 1,
+            overlap: 8,
+            beacons_per_scanner: 27,
+            seed: 3,
+        })
+        .unwrap();
+        assert!(synthetic.scanners.iter().all(|s| s.beacons.len() == 27));
+    }
+
     #[rstest]
     #[case("src/example_input", 79, 3621)]
     #[case("../all_inputs/aoc_19_input.txt", 303, 9621)]
@@ -0,0 +1,17 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+
+extern crate aoc_20;
+use aoc_20::*;
+
+fn speed_test_day_20(c: &mut Criterion) {
+    let input_str = read_file("src/input");
+    c.bench_function("day 20 part 1", |b| {
+        b.iter(|| aoc_20(black_box(&input_str), 2))
+    });
+    c.bench_function("day 20 part 2", |b| {
+        b.iter(|| aoc_20(black_box(&input_str), 50))
+    });
+}
+
+criterion_group!(benches, speed_test_day_20);
+criterion_main!(benches);
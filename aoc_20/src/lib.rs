@@ -1,15 +1,35 @@
-use std::collections::HashMap;
-use std::{fmt, hash};
+use std::fmt;
 use std::{ops::Index, str::FromStr, string::ParseError};
 
 use std::fs::File;
 use std::io::{BufReader, Read};
 
+use std::collections::HashMap;
+
 const TRUE_CHAR: char = '#';
 const FALSE_CHAR: char = '.';
 
-// const TRUE_CHAR: char = '\u{2588}';
-// const FALSE_CHAR: char = '\u{2591}';
+const TRUE_BLOCK: char = '\u{2588}';
+const FALSE_BLOCK: char = '\u{2591}';
+
+/// Characters used to draw lit and dark pixels in the terminal.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Glyphs {
+    Ascii,
+    Blocks,
+    Custom { lit: char, dark: char },
+}
+
+impl Glyphs {
+    fn get(self: &Self, lit: bool) -> char {
+        let (true_char, false_char) = match *self {
+            Glyphs::Ascii => (TRUE_CHAR, FALSE_CHAR),
+            Glyphs::Blocks => (TRUE_BLOCK, FALSE_BLOCK),
+            Glyphs::Custom { lit, dark } => (lit, dark),
+        };
+        return if lit { true_char } else { false_char };
+    }
+}
 
 pub fn read_file(file: &str) -> String {
     let input = File::open(file).unwrap();
@@ -40,10 +60,215 @@ impl Input {
     fn enhance(self: &Self, iterations: u16) -> Image {
         let mut image = self.image.clone();
         for _ in 0..iterations {
-            image = image.enhance(&self.iea);
+            image = image.step(&self.iea);
         }
         return image;
     }
+
+    /// The input image followed by the result of every iteration.
+    fn frames(self: &Self, iterations: u16) -> Vec<Image> {
+        let mut frames = vec![self.image.clone()];
+        for _ in 0..iterations {
+            frames.push(frames.last().unwrap().step(&self.iea));
+        }
+        return frames;
+    }
+}
+
+/// Grey levels of a dark and lit pixel, then of a dark and lit pixel of the infinite
+/// background outside the bounding box of a frame.
+const SHADES: [u8; 4] = [0, 255, 64, 192];
+
+/// Every iteration of an enhancement, drawn on the bounding box of the last one so the
+/// frames line up and the background around the earlier ones is visible.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Animation {
+    frames: Vec<Image>,
+    bounds: ((i16, i16), (i16, i16)),
+}
+
+impl Animation {
+    pub fn new(input_str: &str, iterations: u16) -> Self {
+        let frames = Input::from_str(input_str).unwrap().frames(iterations);
+        let bounds = frames.last().unwrap().get_boundaries();
+        return Animation { frames, bounds };
+    }
+
+    pub fn len(self: &Self) -> usize {
+        return self.frames.len();
+    }
+
+    pub fn is_empty(self: &Self) -> bool {
+        return self.frames.is_empty();
+    }
+
+    fn size(self: &Self) -> (usize, usize) {
+        let ((min_i, max_i), (min_j, max_j)) = self.bounds;
+        return ((max_j - min_j) as usize, (max_i - min_i) as usize);
+    }
+
+    /// Index into `SHADES` of every pixel of a frame, row by row.
+    fn shades(self: &Self, frame: &Image) -> Vec<u8> {
+        let ((min_i, max_i), (min_j, max_j)) = self.bounds;
+        let mut shades = vec![];
+        for i in min_i..max_i {
+            for j in min_j..max_j {
+                let outside = !frame.in_boundries((i, j));
+                shades.push((outside as u8) << 1 | frame[(i, j)] as u8);
+            }
+        }
+        return shades;
+    }
+
+    pub fn to_terminal(self: &Self, glyphs: Glyphs) -> Vec<String> {
+        return self
+            .frames
+            .iter()
+            .map(|frame| frame.render(glyphs, self.bounds))
+            .collect();
+    }
+
+    /// One binary PBM file per frame, lit pixels are black.
+    pub fn to_pbm(self: &Self) -> Vec<Vec<u8>> {
+        let (width, height) = self.size();
+        return self
+            .frames
+            .iter()
+            .map(|frame| {
+                let mut pbm = format!("P4\n{} {}\n", width, height).into_bytes();
+                for row in self.shades(frame).chunks(width) {
+                    for byte in row.chunks(8) {
+                        let packed = byte
+                            .iter()
+                            .enumerate()
+                            .fold(0u8, |acc, (k, shade)| acc | (shade & 1) << (7 - k));
+                        pbm.push(packed);
+                    }
+                }
+                return pbm;
+            })
+            .collect();
+    }
+
+    /// One binary PGM file per frame, the background outside the bounding box of the frame
+    /// is drawn in grey.
+    pub fn to_pgm(self: &Self) -> Vec<Vec<u8>> {
+        let (width, height) = self.size();
+        return self
+            .frames
+            .iter()
+            .map(|frame| {
+                let mut pgm = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+                pgm.extend(
+                    self.shades(frame)
+                        .iter()
+                        .map(|&shade| SHADES[shade as usize]),
+                );
+                return pgm;
+            })
+            .collect();
+    }
+
+    /// Looping GIF with the shades of `to_pgm`, `delay` is in hundredths of a second.
+    pub fn to_gif(self: &Self, delay: u16) -> Vec<u8> {
+        let (width, height) = self.size();
+        let mut gif = b"GIF89a".to_vec();
+        gif.extend((width as u16).to_le_bytes());
+        gif.extend((height as u16).to_le_bytes());
+        // Global colour table of 4 entries
+        gif.extend([0b1000_0001, 0, 0]);
+        for shade in SHADES {
+            gif.extend([shade; 3]);
+        }
+        gif.extend([0x21, 0xFF, 0x0B]);
+        gif.extend(b"NETSCAPE2.0");
+        gif.extend([0x03, 0x01, 0x00, 0x00, 0x00]);
+
+        for frame in &self.frames {
+            gif.extend([0x21, 0xF9, 0x04, 0x00]);
+            gif.extend(delay.to_le_bytes());
+            gif.extend([0x00, 0x00]);
+            gif.extend([0x2C, 0, 0, 0, 0]);
+            gif.extend((width as u16).to_le_bytes());
+            gif.extend((height as u16).to_le_bytes());
+            gif.push(0);
+            gif.push(GIF_MIN_CODE_SIZE);
+            for block in lzw_encode(&self.shades(frame), GIF_MIN_CODE_SIZE).chunks(255) {
+                gif.push(block.len() as u8);
+                gif.extend(block);
+            }
+            gif.push(0);
+        }
+        gif.push(0x3B);
+        return gif;
+    }
+}
+
+const GIF_MIN_CODE_SIZE: u8 = 2;
+const GIF_MAX_CODE: u16 = (1 << 12) - 1;
+
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    buffered: u8,
+}
+
+impl BitWriter {
+    fn write(self: &mut Self, code: u16, width: u8) {
+        self.buffer |= (code as u32) << self.buffered;
+        self.buffered += width;
+        while self.buffered >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.buffered -= 8;
+        }
+    }
+
+    fn finish(mut self: Self) -> Vec<u8> {
+        if self.buffered > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        return self.bytes;
+    }
+}
+
+/// Variable width LZW as used by GIF, codes are packed least significant bit first.
+fn lzw_encode(symbols: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear: u16 = 1 << min_code_size;
+    let end = clear + 1;
+    let mut writer = BitWriter::default();
+    let mut table = HashMap::<(u16, u8), u16>::new();
+    let mut width = min_code_size + 1;
+    let mut last = end;
+    writer.write(clear, width);
+    let mut symbols = symbols.iter();
+    if let Some(&first) = symbols.next() {
+        let mut code = first as u16;
+        for &symbol in symbols {
+            if let Some(&known) = table.get(&(code, symbol)) {
+                code = known;
+                continue;
+            }
+            writer.write(code, width);
+            last += 1;
+            if last == 1 << width {
+                width += 1;
+            }
+            if last == GIF_MAX_CODE {
+                writer.write(clear, width);
+                table.clear();
+                width = min_code_size + 1;
+                last = end;
+            } else {
+                table.insert((code, symbol), last);
+            }
+            code = symbol as u16;
+        }
+        writer.write(code, width);
+    }
+    writer.write(end, width);
+    return writer.finish();
 }
 
 impl FromStr for Input {
@@ -84,29 +309,182 @@ impl IEA {
     }
 }
 
+/// Largest supported neighbourhood radius, the 7x7 neighbourhood still fits in a `u64`.
+const MAX_RADIUS: usize = 3;
+
+/// A cellular automaton rule on a square neighbourhood of side `2 * radius + 1`.
+pub trait Rule {
+    fn radius(self: &Self) -> usize;
+
+    /// New state of a pixel. The neighbourhood is read row by row from the top left, the
+    /// top left pixel is the most significant bit.
+    fn apply(self: &Self, neighbourhood: u64) -> bool;
+
+    /// New state of the infinite background. By default the rule is applied to a
+    /// neighbourhood of background pixels, rules that want a fixed background override this.
+    fn background(self: &Self, background: bool) -> bool {
+        let size = 2 * self.radius() + 1;
+        return match background {
+            true => self.apply((1 << (size * size)) - 1),
+            false => self.apply(0),
+        };
+    }
+}
+
+impl Rule for IEA {
+    fn radius(self: &Self) -> usize {
+        return 1;
+    }
+
+    fn apply(self: &Self, neighbourhood: u64) -> bool {
+        return self.enhancer[neighbourhood as usize];
+    }
+
+    fn background(self: &Self, background: bool) -> bool {
+        return self.enhance(vec![background; 9]);
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
-struct Image {
-    hash: HashMap<(i16, i16), bool>,
-    background: bool,
+pub struct InvalidRule {
+    pub rule: String,
 }
 
-impl fmt::Display for Image {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let ((min_i, max_i), (min_j, max_j)) = self.get_boundaries();
-        let mut out = String::new();
-        for i in min_i..max_i {
-            for j in min_j..max_j {
-                // if self.hash.contains(&(i, j)) {
-                if **self.hash.get(&(i, j)).get_or_insert(&self.background) {
-                    out.push(TRUE_CHAR);
-                } else {
-                    out.push(FALSE_CHAR);
+/// Outer totalistic rule on the 3x3 neighbourhood in B/S notation, e.g. `B3/S23` for Life.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct LifeLike {
+    /// Neighbour counts that light a dark pixel
+    pub birth: [bool; 9],
+    /// Neighbour counts that keep a lit pixel lit
+    pub survival: [bool; 9],
+}
+
+impl FromStr for LifeLike {
+    type Err = InvalidRule;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || InvalidRule {
+            rule: s.to_string(),
+        };
+        let counts = |part: &str, prefix: char| -> Result<[bool; 9], InvalidRule> {
+            let digits = part
+                .strip_prefix(prefix)
+                .or_else(|| part.strip_prefix(prefix.to_ascii_lowercase()))
+                .ok_or_else(invalid)?;
+            let mut counts = [false; 9];
+            for digit in digits.chars() {
+                match digit.to_digit(10) {
+                    Some(count) if count < 9 => counts[count as usize] = true,
+                    _ => return Err(invalid()),
                 }
             }
-            out.push('\n');
+            return Ok(counts);
+        };
+        let (birth, survival) = s.trim().split_once('/').ok_or_else(invalid)?;
+        return Ok(LifeLike {
+            birth: counts(birth, 'B')?,
+            survival: counts(survival, 'S')?,
+        });
+    }
+}
+
+impl Rule for LifeLike {
+    fn radius(self: &Self) -> usize {
+        return 1;
+    }
+
+    fn apply(self: &Self, neighbourhood: u64) -> bool {
+        let centre = neighbourhood >> 4 & 1 == 1;
+        let neighbours = (neighbourhood & !(1 << 4)).count_ones() as usize;
+        return match centre {
+            true => self.survival[neighbours],
+            false => self.birth[neighbours],
+        };
+    }
+}
+
+/// Totalistic rule, a pixel is lit when the number of lit pixels in its neighbourhood,
+/// itself included, is one of `sums`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Totalistic {
+    pub radius: usize,
+    pub sums: Vec<bool>,
+}
+
+impl Totalistic {
+    /// Fails for radii outside `1..=3` and for sums larger than the neighbourhood.
+    pub fn new(radius: usize, lit_sums: &[usize]) -> Result<Self, InvalidRule> {
+        let size = 2 * radius + 1;
+        if !(1..=MAX_RADIUS).contains(&radius) || lit_sums.iter().any(|&sum| sum > size * size) {
+            return Err(InvalidRule {
+                rule: format!("sums {:?} for radius {}", lit_sums, radius),
+            });
+        }
+        let mut sums = vec![false; size * size + 1];
+        for &sum in lit_sums {
+            sums[sum] = true;
+        }
+        return Ok(Totalistic { radius, sums });
+    }
+}
+
+impl Rule for Totalistic {
+    fn radius(self: &Self) -> usize {
+        return self.radius;
+    }
+
+    fn apply(self: &Self, neighbourhood: u64) -> bool {
+        return self.sums[neighbourhood.count_ones() as usize];
+    }
+}
+
+/// Arbitrary lookup table indexed by the neighbourhood, the 3x3 IEA of the puzzle is the
+/// table of radius 1.
+#[derive(Debug, PartialEq, Clone)]
+pub struct RuleTable {
+    radius: usize,
+    table: Vec<bool>,
+}
+
+impl RuleTable {
+    /// Radius 1 takes 512 entries and radius 2 takes 2^25.
+    pub fn new(radius: usize, table: Vec<bool>) -> Result<Self, InvalidRule> {
+        let size = 2 * radius + 1;
+        if !(1..=2).contains(&radius) || table.len() != 1 << (size * size) {
+            return Err(InvalidRule {
+                rule: format!("table of {} entries for radius {}", table.len(), radius),
+            });
         }
+        return Ok(RuleTable { radius, table });
+    }
+}
 
-        write!(f, "{}", out)
+impl Rule for RuleTable {
+    fn radius(self: &Self) -> usize {
+        return self.radius;
+    }
+
+    fn apply(self: &Self, neighbourhood: u64) -> bool {
+        return self.table[neighbourhood as usize];
+    }
+}
+
+/// Dense image of the pixels in a bounding box, every pixel outside the box is `background`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Image {
+    /// Row major, each row starts at a new word and unused bits stay zero
+    bits: Vec<u64>,
+    words_per_row: usize,
+    width: usize,
+    height: usize,
+    /// Coordinates of the top left pixel of the box
+    origin: (i16, i16),
+    background: bool,
+}
+
+impl fmt::Display for Image {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.render(Glyphs::Ascii, self.get_boundaries()))
     }
 }
 
@@ -114,67 +492,158 @@ impl FromStr for Image {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let hash: HashMap<(i16, i16), bool> = s
-            .lines()
-            .enumerate()
-            .flat_map(|(i, c)| {
-                c.chars()
-                    .enumerate()
-                    .map(move |(j, c)| ((i as i16, j as i16), c == '#'))
-            })
-            .fold(HashMap::<(i16, i16), bool>::new(), |mut m, (idx, c)| {
-                m.insert(idx, c);
-                m
-            });
-
-        let img = Image {
-            hash,
-            background: false,
-        };
+        let rows = s.lines().collect::<Vec<&str>>();
+        let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+        let mut img = Image::new(width, rows.len(), (0, 0), false);
+        for (i, row) in rows.iter().enumerate() {
+            for (j, c) in row.chars().enumerate() {
+                img.set(i, j, c == '#');
+            }
+        }
         return Ok(img);
     }
 }
 
 impl Image {
-    fn enhance(self: &Self, enhancer: &IEA) -> Self {
-        let mut new_hash = HashMap::<(i16, i16), bool>::with_capacity(self.hash.len());
+    fn new(width: usize, height: usize, origin: (i16, i16), background: bool) -> Self {
+        let words_per_row = width.div_ceil(64);
+        return Self {
+            bits: vec![0; words_per_row * height],
+            words_per_row,
+            width,
+            height,
+            origin,
+            background,
+        };
+    }
 
-        let ((min_i, max_i), (min_j, max_j)) = self.get_boundaries();
+    fn set(self: &mut Self, row: usize, col: usize, on: bool) {
+        let word = row * self.words_per_row + col / 64;
+        if on {
+            self.bits[word] |= 1 << (col % 64);
+        } else {
+            self.bits[word] &= !(1 << (col % 64));
+        }
+    }
 
-        for i in min_i - 1..max_i + 1 {
-            for j in min_j - 1..max_j + 1 {
-                let mut iea_input = vec![];
-                for i_off in -1..2 {
-                    for j_off in -1..2 {
-                        iea_input.push(self[(i + i_off, j + j_off)])
-                    }
+    /// Words of a row of the box, `None` above or below it.
+    fn row(self: &Self, row: isize) -> Option<&[u64]> {
+        if row < 0 || row as usize >= self.height {
+            return None;
+        }
+        let start = row as usize * self.words_per_row;
+        return Some(&self.bits[start..start + self.words_per_row]);
+    }
+
+    fn bit(self: &Self, words: Option<&[u64]>, col: isize) -> bool {
+        return match words {
+            Some(words) if col >= 0 && (col as usize) < self.width => {
+                words[col as usize / 64] >> (col % 64) & 1 == 1
+            }
+            _ => self.background,
+        };
+    }
+
+    /// Pixel at `row`, `col` relative to the box, which may lie outside of it.
+    fn get(self: &Self, row: isize, col: isize) -> bool {
+        return self.bit(self.row(row), col);
+    }
+
+    /// Bits of the image with a border of `pad` background pixels on every side, and the
+    /// number of words per row, so neighbourhoods can be read without bounds checks.
+    fn padded(self: &Self, pad: usize) -> (Vec<u64>, usize) {
+        let words_per_row = (self.width + 2 * pad).div_ceil(64);
+        let fill = if self.background { u64::MAX } else { 0 };
+        let mut bits = vec![fill; words_per_row * (self.height + 2 * pad)];
+        for (row, source) in self.bits.chunks(self.words_per_row.max(1)).enumerate() {
+            let target = &mut bits[(row + pad) * words_per_row..(row + pad + 1) * words_per_row];
+            target.fill(0);
+            for (k, word) in source.iter().enumerate() {
+                target[k] |= word << pad;
+                if let Some(next) = target.get_mut(k + 1) {
+                    *next |= word >> (64 - pad);
+                }
+            }
+            if self.background {
+                target[0] |= (1 << pad) - 1;
+                for col in self.width + pad..words_per_row * 64 {
+                    target[col / 64] |= 1 << (col % 64);
                 }
-                new_hash.insert((i, j), enhancer.enhance(iea_input));
             }
         }
+        return (bits, words_per_row);
+    }
 
-        return Self {
-            hash: new_hash,
-            background: enhancer.enhance(vec![self.background].repeat(9)),
+    /// Applies `rule` once, the box grows by the radius of the rule on every side. The
+    /// neighbourhood index is slid along each row, so every pixel only reads the column
+    /// entering on the right.
+    pub fn step<R: Rule>(self: &Self, rule: &R) -> Self {
+        let radius = rule.radius();
+        assert!(
+            (1..=MAX_RADIUS).contains(&radius),
+            "unsupported radius {}",
+            radius
+        );
+        let size = 2 * radius + 1;
+        let mut stepped = Image::new(
+            self.width + 2 * radius,
+            self.height + 2 * radius,
+            (self.origin.0 - radius as i16, self.origin.1 - radius as i16),
+            rule.background(self.background),
+        );
+
+        // Clears the bits that slide out of the left of every row of the neighbourhood
+        let keep = (0..size).fold((1u64 << (size * size)) - 1, |mask, k| {
+            mask & !(1 << (k * size))
+        });
+        let (padded, words_per_row) = self.padded(2 * radius);
+        let bit = |row: usize, col: usize| -> u64 {
+            return padded[row * words_per_row + col / 64] >> (col % 64) & 1;
         };
-    }
+        for row in 0..stepped.height {
+            let column = |col: usize| -> u64 {
+                return (row..row + size).fold(0, |acc, r| acc << size | bit(r, col));
+            };
+            let mut index = (0..size - 1).fold(0, |index, col| index << 1 | column(col));
+            for col in 0..stepped.width {
+                index = (index << 1) & keep | column(col + size - 1);
+                stepped.bits[row * stepped.words_per_row + col / 64] |=
+                    (rule.apply(index) as u64) << (col % 64);
+            }
+        }
 
-    fn get_boundaries(self: &Self) -> ((i16, i16), (i16, i16)) {
-        let min_i = self.hash.iter().min_by_key(|((i, _), _)| i).unwrap().0 .0;
-        let min_j = self.hash.iter().min_by_key(|((_, j), _)| j).unwrap().0 .1;
-        let max_i = self.hash.iter().max_by_key(|((i, _), _)| i).unwrap().0 .0 + 1;
-        let max_j = self.hash.iter().max_by_key(|((_, j), _)| j).unwrap().0 .1 + 1;
+        return stepped;
+    }
 
-        return ((min_i, max_i), (min_j, max_j));
+    /// Rows and columns of the bounding box, the ends are exclusive.
+    pub fn get_boundaries(self: &Self) -> ((i16, i16), (i16, i16)) {
+        let (min_i, min_j) = self.origin;
+        return (
+            (min_i, min_i + self.height as i16),
+            (min_j, min_j + self.width as i16),
+        );
     }
 
     fn in_boundries(self: &Self, index: (i16, i16)) -> bool {
         let ((min_i, max_i), (min_j, max_j)) = self.get_boundaries();
-        return min_i < index.0 && index.0 < max_i && min_j < index.1 && index.1 < max_j;
+        return min_i <= index.0 && index.0 < max_i && min_j <= index.1 && index.1 < max_j;
     }
 
-    fn count_light_pixels(self: &Self) -> u32 {
-        return self.hash.iter().filter(|(_, &on)| on).count() as u32;
+    fn render(self: &Self, glyphs: Glyphs, bounds: ((i16, i16), (i16, i16))) -> String {
+        let ((min_i, max_i), (min_j, max_j)) = bounds;
+        let mut out = String::new();
+        for i in min_i..max_i {
+            for j in min_j..max_j {
+                out.push(glyphs.get(self[(i, j)]));
+            }
+            out.push('\n');
+        }
+        return out;
+    }
+
+    /// Lit pixels inside the bounding box.
+    pub fn count_light_pixels(self: &Self) -> u32 {
+        return self.bits.iter().map(|word| word.count_ones()).sum();
     }
 }
 
@@ -182,7 +651,14 @@ impl Index<(i16, i16)> for Image {
     type Output = bool;
 
     fn index(&self, index: (i16, i16)) -> &Self::Output {
-        return self.hash.get(&index).get_or_insert(&self.background);
+        if !self.in_boundries(index) {
+            return &self.background;
+        }
+        let lit = self.get(
+            (index.0 - self.origin.0) as isize,
+            (index.1 - self.origin.1) as isize,
+        );
+        return if lit { &true } else { &false };
     }
 }
 
@@ -209,6 +685,143 @@ mod tests {
         )
     }
 
+    #[rstest]
+    #[case("#..#.\n#....\n##..#\n..#..\n..###\n")]
+    fn test_display_image(#[case] input_str: &str) {
+        let image = Image::from_str(input_str).unwrap();
+        assert_eq!(image.to_string(), input_str);
+        assert!(image[(0, 0)]);
+        assert!(!image[(-5, 70)]);
+    }
+
+    #[test]
+    fn test_enhance_background() {
+        let input = Input::from_str(&read_file("src/input")).unwrap();
+        let once = input.enhance(1);
+        assert_eq!(once.get_boundaries(), ((-1, 101), (-1, 101)));
+        assert_eq!(once[(-10, -10)], input.iea.enhancer[0]);
+        assert_eq!(input.enhance(2)[(500, 500)], false);
+    }
+
+    #[rstest]
+    #[case(Glyphs::Ascii, "#..#.\n")]
+    #[case(Glyphs::Blocks, "\u{2588}\u{2591}\u{2591}\u{2588}\u{2591}\n")]
+    #[case(Glyphs::Custom { lit: 'x', dark: ' ' }, "x  x \n")]
+    fn test_glyphs(#[case] glyphs: Glyphs, #[case] first_row: &str) {
+        let animation = Animation::new(&read_file("src/example_input"), 0);
+        let frame = &animation.to_terminal(glyphs)[0];
+        assert!(frame.starts_with(first_row));
+    }
+
+    #[test]
+    fn test_animation() {
+        let input_str = read_file("src/input");
+        let animation = Animation::new(&input_str, 2);
+        assert_eq!(animation.len(), 3);
+
+        let frames = animation.to_terminal(Glyphs::Ascii);
+        let last = Input::from_str(&input_str).unwrap().enhance(2);
+        assert_eq!(frames[2], last.to_string());
+        // The first iteration lights the infinite background of this input
+        assert!(frames[1].starts_with("##"));
+        assert!(frames[0].starts_with(".."));
+
+        let pbm = &animation.to_pbm()[0];
+        assert!(pbm.starts_with(b"P4\n104 104\n"));
+        assert_eq!(pbm.len(), "P4\n104 104\n".len() + 13 * 104);
+        let pgm = &animation.to_pgm()[1];
+        assert!(pgm.starts_with(b"P5\n104 104\n255\n"));
+        assert_eq!(pgm["P5\n104 104\n255\n".len()], 192);
+
+        let gif = animation.to_gif(10);
+        assert!(gif.starts_with(b"GIF89a"));
+        assert_eq!(gif.last(), Some(&0x3B));
+    }
+
+    #[rstest]
+    #[case(vec![0, 0, 0, 0], vec![0x84, 0x51])]
+    #[case(vec![1, 2, 1, 2, 1, 2], vec![0x8c, 0x6c, 0x05])]
+    fn test_lzw_encode(#[case] symbols: Vec<u8>, #[case] expected: Vec<u8>) {
+        assert_eq!(lzw_encode(&symbols, 2), expected);
+    }
+
+    fn lit_pixels(image: &Image) -> Vec<(i16, i16)> {
+        let ((min_i, max_i), (min_j, max_j)) = image.get_boundaries();
+        return (min_i..max_i)
+            .flat_map(|i| (min_j..max_j).map(move |j| (i, j)))
+            .filter(|&index| image[index])
+            .collect();
+    }
+
+    #[rstest]
+    #[case("B3/S23", Ok(LifeLike { birth: [false, false, false, true, false, false, false, false, false], survival: [false, false, true, true, false, false, false, false, false] }))]
+    #[case("b0/s8", Ok(LifeLike { birth: [true, false, false, false, false, false, false, false, false], survival: [false, false, false, false, false, false, false, false, true] }))]
+    #[case("B3S23", Err(InvalidRule { rule: "B3S23".to_string() }))]
+    #[case("B9/S23", Err(InvalidRule { rule: "B9/S23".to_string() }))]
+    #[case("S23/B3", Err(InvalidRule { rule: "S23/B3".to_string() }))]
+    fn test_parse_life_like(
+        #[case] input_str: &str,
+        #[case] expected: Result<LifeLike, InvalidRule>,
+    ) {
+        assert_eq!(LifeLike::from_str(input_str), expected);
+    }
+
+    #[test]
+    fn test_life_glider() {
+        let life = LifeLike::from_str("B3/S23").unwrap();
+        let glider = Image::from_str(".#.\n..#\n###").unwrap();
+        let mut image = glider.clone();
+        for _ in 0..4 {
+            image = image.step(&life);
+        }
+        let moved = lit_pixels(&glider)
+            .iter()
+            .map(|(i, j)| (i + 1, j + 1))
+            .collect::<Vec<_>>();
+        assert_eq!(lit_pixels(&image), moved);
+        assert_eq!(image.get_boundaries(), ((-4, 7), (-4, 7)));
+    }
+
+    #[test]
+    fn test_rule_background() {
+        let flashing = LifeLike::from_str("B0/S").unwrap();
+        let image = Image::from_str("...\n.#.\n...").unwrap();
+        let once = image.step(&flashing);
+        assert!(once[(-100, 100)]);
+        // Only pixels without lit neighbours are born, the centre dies
+        assert_eq!(lit_pixels(&once).len(), 5 * 5 - 9);
+        assert!(!once.step(&flashing)[(-100, 100)]);
+    }
+
+    #[test]
+    fn test_totalistic_radius_2() {
+        let rule = Totalistic::new(2, &[1]).unwrap();
+        let image = Image::from_str("#").unwrap().step(&rule);
+        assert_eq!(image.get_boundaries(), ((-2, 3), (-2, 3)));
+        assert_eq!(image.count_light_pixels(), 25);
+        assert!(!image[(10, 10)]);
+        // Only the far corners overlap the lit block in a single pixel
+        assert_eq!(image.step(&rule).count_light_pixels(), 4);
+    }
+
+    #[test]
+    fn test_rule_table() {
+        let input = Input::from_str(&read_file("src/example_input")).unwrap();
+        let table = RuleTable::new(1, input.iea.enhancer.clone()).unwrap();
+        assert_eq!(input.image.step(&table), input.image.step(&input.iea));
+        assert!(RuleTable::new(1, vec![false; 10]).is_err());
+        assert!(RuleTable::new(0, vec![false; 2]).is_err());
+    }
+
+    #[rstest]
+    #[case(0, &[0])]
+    #[case(4, &[1])]
+    #[case(1, &[10])]
+    #[case(3, &[50])]
+    fn test_totalistic_invalid(#[case] radius: usize, #[case] sums: &[usize]) {
+        assert!(Totalistic::new(radius, sums).is_err());
+    }
+
     #[rstest]
     #[case("src/example_input", 35)]
     #[case("src/input", 5846)]
@@ -1,10 +1,10 @@
 use itertools::Itertools;
 use std::collections::HashMap;
-use std::str::ParseBoolError;
-use std::{iter::Repeat, str::FromStr, string::ParseError};
+use std::num::ParseIntError;
+use std::{str::FromStr, string::ParseError};
 
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, Read};
 pub fn read_file(file: &str) -> String {
     let input = File::open(file).unwrap();
 
@@ -14,81 +14,293 @@ pub fn read_file(file: &str) -> String {
     return contents;
 }
 
-pub fn aoc_21_comp(input_str: &str) -> (u32, u64) {
+pub fn aoc_21_comp(input_str: &str) -> (u32, u128) {
     return (day_21_part_1(input_str), day_21_part_2(input_str));
 }
 
+/// Rules of a game of Dirac Dice.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct GameConfig {
+    /// Spaces on the circular track, numbered from 1
+    pub board_size: u16,
+    pub die_faces: u16,
+    pub rolls_per_turn: u16,
+    /// A player wins once their score reaches this
+    pub target_score: u16,
+    pub players: usize,
+}
+
+impl GameConfig {
+    /// The game of part 1, played with the 100 sided deterministic die.
+    pub fn deterministic() -> Self {
+        return GameConfig {
+            board_size: 10,
+            die_faces: 100,
+            rolls_per_turn: 3,
+            target_score: 1000,
+            players: 2,
+        };
+    }
+
+    /// The game of part 2, played with the 3 sided Dirac die.
+    pub fn quantum() -> Self {
+        return GameConfig {
+            target_score: 21,
+            die_faces: 3,
+            ..GameConfig::deterministic()
+        };
+    }
+
+    /// Every total of one turn of rolls, with the number of universes it happens in.
+    pub fn throw_distribution(self: &Self) -> Vec<(u16, u128)> {
+        let mut distribution = HashMap::from([(0, 1)]);
+        for _ in 0..self.rolls_per_turn {
+            let mut next = HashMap::new();
+            for (total, count) in distribution {
+                for face in 1..=self.die_faces {
+                    *next.entry(total + face).or_insert(0) += count;
+                }
+            }
+            distribution = next;
+        }
+        return distribution.into_iter().sorted().collect();
+    }
+
+    fn advance(self: &Self, position: u16, steps: u16) -> u16 {
+        return ((position - 1 + steps % self.board_size) % self.board_size) + 1;
+    }
+
+    fn check_players(self: &Self, found: usize) {
+        assert_eq!(found, self.players, "expected {} players", self.players);
+    }
+}
+
 pub fn day_21_part_1(input_str: &str) -> u32 {
-    let (player1_str, player2_str) = input_str.split_once("\n").unwrap();
+    let config = GameConfig {
+        players: parse_players(input_str).len(),
+        ..GameConfig::deterministic()
+    };
+    return day_21_part_1_with(input_str, &config);
+}
 
-    let mut player1 = Player::from_str(player1_str).unwrap();
-    let mut player2 = Player::from_str(player2_str).unwrap();
-    let mut die = DeterministicDice::new();
-    return game(&mut die, &mut player1, &mut player2);
+pub fn day_21_part_1_with(input_str: &str, config: &GameConfig) -> u32 {
+    let mut players = parse_players(input_str);
+    config.check_players(players.len());
+    let mut die = DeterministicDice::new(config.die_faces);
+    return game(&mut die, &mut players, config);
 }
 
-pub fn day_21_part_2(input_str: &str) -> u64 {
+pub fn day_21_part_2(input_str: &str) -> u128 {
     let initial_state = GameState::from_str(&input_str).unwrap();
     return game_iteration_part_2(initial_state);
 }
 
-fn game(die: &mut impl Throw, player1: &mut Player, player2: &mut Player) -> u32 {
-    let mut die_iter = die.iter(3);
+/// Universe counts outgrow `u64` with more than two players.
+pub fn day_21_part_2_with(input_str: &str, config: &GameConfig) -> u128 {
+    let initial_state = GameState::from_str(&input_str).unwrap();
+    config.check_players(initial_state.positions.len());
+    return game_iteration_part_2_with(initial_state, config);
+}
+
+/// One player per line, in the order they take turns.
+fn parse_players(input_str: &str) -> Vec<Player> {
+    return input_str
+        .trim()
+        .lines()
+        .map(|line| Player::from_str(line).unwrap())
+        .collect();
+}
 
-    let winner;
+/// Plays until someone wins, returns the lowest score of the others times the number of rolls.
+fn game(die: &mut impl Throw, players: &mut [Player], config: &GameConfig) -> u32 {
+    let winner = play(die, players, config).expect("the die ran out of rolls");
+
+    let losing_score = players
+        .iter()
+        .filter(|player| player.id != winner)
+        .map(|player| player.score)
+        .min()
+        .unwrap_or(0);
+    return losing_score as u32 * die.get_count() as u32;
+}
+
+/// Id of the winning player, `None` if the die runs out of rolls first.
+fn play(die: &mut impl Throw, players: &mut [Player], config: &GameConfig) -> Option<u8> {
+    let mut die_iter = die.iter(config.rolls_per_turn);
     loop {
-        if player1.step(die_iter.next().unwrap()) {
-            winner = player1.id;
-            break;
-        };
-        if player2.step(die_iter.next().unwrap()) {
-            winner = player2.id;
-            break;
-        };
+        for player in players.iter_mut() {
+            if player.step(die_iter.next()?, config) {
+                return Some(player.id);
+            };
+        }
     }
+}
 
-    if winner == player1.id {
-        return player2.score as u32 * die.get_count() as u32;
-    } else {
-        return player1.score as u32 * die.get_count() as u32;
+/// Estimates the chance of each player to win by playing `games` games with `die`. Games
+/// cut short by the die running out are not counted.
+pub fn monte_carlo(
+    die: &mut impl Throw,
+    positions: &[u16],
+    config: &GameConfig,
+    games: usize,
+) -> Vec<f64> {
+    let mut wins = vec![0; positions.len()];
+    let mut played = 0;
+    for _ in 0..games {
+        let mut players = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &position)| Player::new(i as u8 + 1, position))
+            .collect::<Vec<Player>>();
+        if let Some(winner) = play(die, &mut players, config) {
+            wins[winner as usize - 1] += 1;
+            played += 1;
+        }
     }
+    return wins
+        .iter()
+        .map(|&won| won as f64 / played.max(1) as f64)
+        .collect();
 }
 
-struct DeterministicDice {
+pub trait Throw {
+    /// The next roll, `None` once the die has no more rolls.
+    fn throw(self: &mut Self) -> Option<u16>;
+
+    fn get_count(&self) -> usize;
+
+    /// Sums of `n` rolls at a time.
+    fn iter(&mut self, n: u16) -> DiceIter<'_, Self>
+    where
+        Self: Sized,
+    {
+        return DiceIter { dice: self, n };
+    }
+}
+
+/// Rolls 1, 2, .. up to the number of faces and starts over.
+pub struct DeterministicDice {
     current: u16,
+    faces: u16,
     count: usize,
 }
 
 impl DeterministicDice {
-    fn new() -> Self {
+    pub fn new(faces: u16) -> Self {
         return Self {
             current: 1,
+            faces,
             count: 0,
         };
     }
 }
 
-trait Throw {
-    fn throw(self: &mut Self) -> u16;
-    fn iter(&mut self, n: u16) -> DiceIter<'_, Self>
-    where
-        Self: Sized;
-    fn get_count(&self) -> usize;
-}
-
 impl Throw for DeterministicDice {
-    fn throw(self: &mut Self) -> u16 {
+    fn throw(self: &mut Self) -> Option<u16> {
         let throw = self.current;
         self.current += 1;
         self.count += 1;
-        if self.current > 100 {
+        if self.current > self.faces {
             self.current = 1;
         }
-        return throw;
+        return Some(throw);
     }
 
-    fn iter(&mut self, n: u16) -> DiceIter<'_, DeterministicDice> {
-        return DiceIter { dice: self, n };
+    fn get_count(&self) -> usize {
+        return self.count;
+    }
+}
+
+/// Xorshift generator, keeps the random dice reproducible from a seed.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> XorShift {
+        return XorShift((seed ^ 0x9E37_79B9_7F4A_7C15).max(1));
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        return self.0;
+    }
+}
+
+/// Fair die with pseudo random rolls.
+pub struct SeededDice {
+    rng: XorShift,
+    faces: u16,
+    count: usize,
+}
+
+impl SeededDice {
+    pub fn new(faces: u16, seed: u64) -> Self {
+        return Self {
+            rng: XorShift::new(seed),
+            faces,
+            count: 0,
+        };
+    }
+}
+
+impl Throw for SeededDice {
+    fn throw(self: &mut Self) -> Option<u16> {
+        self.count += 1;
+        return Some((self.rng.next() % self.faces as u64) as u16 + 1);
+    }
+
+    fn get_count(&self) -> usize {
+        return self.count;
+    }
+}
+
+/// Why a loaded or replayed die could not be created.
+#[derive(Debug)]
+pub enum InvalidDice {
+    /// No face has a positive weight
+    NoWeight,
+    /// The weights add up to more than `u64::MAX`
+    WeightOverflow,
+    Io(io::Error),
+    Roll(ParseIntError),
+}
+
+/// Pseudo random die where face `i + 1` comes up with a chance proportional to `weights[i]`.
+pub struct LoadedDice {
+    rng: XorShift,
+    /// Running totals of the weights
+    cumulative: Vec<u64>,
+    count: usize,
+}
+
+impl LoadedDice {
+    pub fn new(weights: &[u64], seed: u64) -> Result<Self, InvalidDice> {
+        let mut cumulative = Vec::with_capacity(weights.len());
+        let mut total: u64 = 0;
+        for weight in weights {
+            total = total
+                .checked_add(*weight)
+                .ok_or(InvalidDice::WeightOverflow)?;
+            cumulative.push(total);
+        }
+        if total == 0 {
+            return Err(InvalidDice::NoWeight);
+        }
+        return Ok(Self {
+            rng: XorShift::new(seed),
+            cumulative,
+            count: 0,
+        });
+    }
+}
+
+impl Throw for LoadedDice {
+    fn throw(self: &mut Self) -> Option<u16> {
+        self.count += 1;
+        let roll = self.rng.next() % self.cumulative.last().unwrap();
+        let face = self.cumulative.partition_point(|&total| total <= roll);
+        return Some(face as u16 + 1);
     }
 
     fn get_count(&self) -> usize {
@@ -96,7 +308,46 @@ impl Throw for DeterministicDice {
     }
 }
 
-struct DiceIter<'a, Dice>
+/// Replays recorded rolls, separated by whitespace or commas.
+pub struct ReplayDice {
+    rolls: Vec<u16>,
+    count: usize,
+}
+
+impl ReplayDice {
+    pub fn from_file(file: &str) -> Result<Self, InvalidDice> {
+        let rolls = std::fs::read_to_string(file).map_err(InvalidDice::Io)?;
+        return ReplayDice::from_str(&rolls).map_err(InvalidDice::Roll);
+    }
+}
+
+impl FromStr for ReplayDice {
+    type Err = ParseIntError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let rolls = s
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|roll| !roll.is_empty())
+            .map(|roll| roll.parse::<u16>())
+            .collect::<Result<Vec<u16>, ParseIntError>>()?;
+        return Ok(ReplayDice { rolls, count: 0 });
+    }
+}
+
+impl Throw for ReplayDice {
+    fn throw(self: &mut Self) -> Option<u16> {
+        let roll = *self.rolls.get(self.count)?;
+        self.count += 1;
+        return Some(roll);
+    }
+
+    fn get_count(&self) -> usize {
+        return self.count;
+    }
+}
+
+/// Sums of a fixed number of rolls, ends when the die runs out.
+pub struct DiceIter<'a, Dice>
 where
     Dice: Throw,
 {
@@ -113,7 +364,7 @@ where
     fn next(&mut self) -> Option<Self::Item> {
         let mut count = 0;
         for _ in 0..self.n {
-            count += self.dice.throw();
+            count += self.dice.throw()?;
         }
         return Some(count);
     }
@@ -130,7 +381,6 @@ impl FromStr for Player {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         let (id, position) = s.strip_prefix("Player ").unwrap().split_once(" ").unwrap();
-        println!("id: {id}\nposition: {position}");
         let id = id.parse::<u8>().expect("Unable to find id");
         let position = position
             .strip_prefix("starting position: ")
@@ -152,55 +402,57 @@ impl Player {
         };
     }
 
-    fn step(self: &mut Self, steps: u16) -> bool {
-        self.position = ((self.position + steps - 1) % 10) + 1;
+    fn step(self: &mut Self, steps: u16, config: &GameConfig) -> bool {
+        self.position = config.advance(self.position, steps);
         self.score += self.position;
-        return self.score >= 1000;
+        return self.score >= config.target_score;
     }
 }
 
-#[derive(Eq, PartialEq, Hash, Clone, Copy, Debug)]
-struct GameState {
-    position1: u16,
-    position2: u16,
-    score1: u8,
-    score2: u8,
-    throws: u8,
-    player1_turn: bool,
+#[derive(Eq, PartialEq, Hash, Clone, Debug)]
+pub struct GameState {
+    pub positions: Vec<u16>,
+    pub scores: Vec<u16>,
+    /// Index of the player to move
+    pub turn: usize,
 }
 
 impl GameState {
-    fn quantum_step(self: Self) -> Vec<(Self, u8)> {
-        const QUANTUM_THROWS: [(u8, u8); 7] =
-            [(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)];
+    pub fn new(positions: Vec<u16>, scores: Vec<u16>, turn: usize) -> Self {
+        return GameState {
+            positions,
+            scores,
+            turn,
+        };
+    }
 
+    fn quantum_step(
+        self: &Self,
+        config: &GameConfig,
+        distribution: &[(u16, u128)],
+    ) -> Vec<(Self, u128)> {
         let mut new_states = vec![];
-        for (i, weight) in QUANTUM_THROWS {
-            new_states.push((self.clone().step(i), weight))
+        for &(i, weight) in distribution {
+            new_states.push((self.clone().step(i, config), weight))
         }
         return new_states;
     }
 
-    fn step(mut self, steps: u8) -> Self {
-        if self.player1_turn {
-            self.position1 = ((self.position1 - 1 + steps as u16) % 10) + 1;
-            self.score1 += self.position1 as u8;
-        } else {
-            self.position2 = ((self.position2 - 1 + steps as u16) % 10) + 1;
-            self.score2 += self.position2 as u8;
-        }
-        self.player1_turn = !self.player1_turn;
-        self.throws += 1;
+    fn step(mut self, steps: u16, config: &GameConfig) -> Self {
+        let position = config.advance(self.positions[self.turn], steps);
+        self.positions[self.turn] = position;
+        self.scores[self.turn] += position;
+        self.turn = (self.turn + 1) % self.positions.len();
         return self;
     }
 
-    fn get_winner(self: &Self) -> Option<u8> {
-        if self.score1 >= 21 {
-            return Some(1);
-        } else if self.score2 >= 21 {
-            return Some(2);
-        }
-        return None;
+    /// Number of the winning player, counting from 1.
+    fn get_winner(self: &Self, config: &GameConfig) -> Option<u8> {
+        return self
+            .scores
+            .iter()
+            .position(|&score| score >= config.target_score)
+            .map(|player| player as u8 + 1);
     }
 }
 
@@ -208,56 +460,36 @@ impl FromStr for GameState {
     type Err = ParseError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let (player1_str, player2_str) = s.trim().split_once("\n").unwrap();
-
-        fn retrieve_id_position(input: &str) -> (u8, u16) {
-            let (id, position) = input
-                .strip_prefix("Player ")
-                .unwrap()
-                .split_once(" ")
-                .unwrap();
-            let id = id.parse::<u8>().expect("Unable to find id");
-            let position = position
-                .strip_prefix("starting position: ")
-                .unwrap()
-                .trim()
-                .parse::<u16>()
-                .expect("Unable to find position");
-            return (id, position);
-        }
-
-        let (_, p1) = retrieve_id_position(player1_str);
-        let (_, p2) = retrieve_id_position(player2_str);
-        return Ok(GameState {
-            position1: p1,
-            position2: p2,
-            score1: 0,
-            score2: 0,
-            throws: 0,
-            player1_turn: true,
-        });
+        let positions = parse_players(s)
+            .iter()
+            .map(|player| player.position)
+            .collect::<Vec<u16>>();
+        let scores = vec![0; positions.len()];
+        return Ok(GameState::new(positions, scores, 0));
     }
 }
 
-fn game_iteration_part_2(initial_state: GameState) -> u64 {
-    let mut states: HashMap<GameState, u64> = HashMap::from([(initial_state, 1)]);
+fn game_iteration_part_2(initial_state: GameState) -> u128 {
+    let config = GameConfig {
+        players: initial_state.positions.len(),
+        ..GameConfig::quantum()
+    };
+    return game_iteration_part_2_with(initial_state, &config);
+}
 
-    let mut p1_games = 0;
-    let mut p2_games = 0;
+fn game_iteration_part_2_with(initial_state: GameState, config: &GameConfig) -> u128 {
+    let distribution = config.throw_distribution();
+    let mut games = vec![0; initial_state.positions.len()];
+    let mut states: HashMap<GameState, u128> = HashMap::from([(initial_state, 1)]);
 
     while !states.is_empty() {
-        let mut new_states = HashMap::<GameState, u64>::new();
+        let mut new_states = HashMap::<GameState, u128>::new();
         for (state, weight) in states.drain() {
-            for (new_state, multiplier) in state.quantum_step() {
-                let weight = weight * multiplier as u64;
-                if let Some(winner) = new_state.get_winner() {
-                    if winner == 1 {
-                        p1_games += weight;
-                    } else {
-                        p2_games += weight;
-                    }
+            for (new_state, multiplier) in state.quantum_step(config, &distribution) {
+                let weight = weight * multiplier;
+                if let Some(winner) = new_state.get_winner(config) {
+                    games[winner as usize - 1] += weight;
                 } else {
-                    // println!("weight: {}", weight);
                     *new_states.entry(new_state).or_insert(0) += weight;
                 }
             }
@@ -265,9 +497,103 @@ fn game_iteration_part_2(initial_state: GameState) -> u64 {
         states = new_states;
     }
 
-    println!("{}, {}", p1_games, p2_games);
+    return *games.iter().max().unwrap();
+}
+
+/// Every universe that can be played out from a state.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Outcome {
+    /// Universes won by each player
+    pub wins: Vec<u128>,
+    /// `turns[player][n]` universes are won by `player` after `n` more turns
+    pub turns: Vec<Vec<u128>>,
+}
+
+impl Outcome {
+    fn new(players: usize) -> Self {
+        return Outcome {
+            wins: vec![0; players],
+            turns: vec![vec![]; players],
+        };
+    }
+
+    fn won(players: usize, winner: usize) -> Self {
+        let mut outcome = Outcome::new(players);
+        outcome.wins[winner] = 1;
+        outcome.turns[winner].push(1);
+        return outcome;
+    }
+
+    /// Adds the universes of the state after one more turn, which happens `weight` times.
+    fn add_turn(self: &mut Self, next: &Outcome, weight: u128) {
+        for (player, turns) in next.turns.iter().enumerate() {
+            self.wins[player] += next.wins[player] * weight;
+            let own = &mut self.turns[player];
+            if own.len() < turns.len() + 1 {
+                own.resize(turns.len() + 1, 0);
+            }
+            for (n, count) in turns.iter().enumerate() {
+                own[n + 1] += count * weight;
+            }
+        }
+    }
+}
+
+/// Plays out the quantum game depth first, remembering the outcome of every state.
+pub struct QuantumSolver {
+    config: GameConfig,
+    distribution: Vec<(u16, u128)>,
+    memo: HashMap<GameState, Outcome>,
+}
+
+impl QuantumSolver {
+    pub fn new(config: GameConfig) -> Self {
+        return QuantumSolver {
+            distribution: config.throw_distribution(),
+            config,
+            memo: HashMap::new(),
+        };
+    }
+
+    pub fn solve(self: &mut Self, state: &GameState) -> Outcome {
+        if let Some(outcome) = self.memo.get(state) {
+            return outcome.clone();
+        }
+
+        let players = state.positions.len();
+        let outcome = match state.get_winner(&self.config) {
+            Some(winner) => Outcome::won(players, winner as usize - 1),
+            None => {
+                let mut outcome = Outcome::new(players);
+                let next_states = state.quantum_step(&self.config, &self.distribution);
+                for (next, weight) in next_states {
+                    outcome.add_turn(&self.solve(&next), weight);
+                }
+                outcome
+            }
+        };
+        self.memo.insert(state.clone(), outcome.clone());
+        return outcome;
+    }
 
-    return p1_games.max(p2_games);
+    /// Chance of each player to win from `state` when the die is rolled for real, games
+    /// that end sooner are more likely than their share of universes suggests.
+    pub fn win_probabilities(self: &mut Self, state: &GameState) -> Vec<f64> {
+        let universes_per_turn =
+            (self.config.die_faces as f64).powi(self.config.rolls_per_turn as i32);
+        return self
+            .solve(state)
+            .turns
+            .iter()
+            .map(|turns| {
+                turns
+                    .iter()
+                    .enumerate()
+                    .map(|(n, &count)| count as f64 / universes_per_turn.powi(n as i32))
+                    .sum()
+            })
+            .collect();
+    }
 }
 
 #[cfg(test)]
@@ -283,7 +609,7 @@ mod tests {
 
     #[test]
     fn test_dice() {
-        let mut die = DeterministicDice::new();
+        let mut die = DeterministicDice::new(100);
 
         let range: Vec<u16> = (1..101).cycle().take(1000).collect();
         let die_throws: Vec<u16> = die.iter(1).take(1000).collect();
@@ -292,6 +618,163 @@ mod tests {
         assert_eq!(die.count, 1_000);
     }
 
+    #[rstest]
+    #[case(GameConfig::quantum(), vec![(3, 1), (4, 3), (5, 6), (6, 7), (7, 6), (8, 3), (9, 1)])]
+    #[case(GameConfig { die_faces: 2, rolls_per_turn: 2, ..GameConfig::quantum() }, vec![(2, 1), (3, 2), (4, 1)])]
+    #[case(GameConfig { rolls_per_turn: 1, ..GameConfig::deterministic() }, (1..=100).map(|i| (i, 1)).collect())]
+    fn test_throw_distribution(#[case] config: GameConfig, #[case] expected: Vec<(u16, u128)>) {
+        assert_eq!(config.throw_distribution(), expected);
+    }
+
+    #[rstest]
+    #[case(GameConfig::deterministic(), 739785)]
+    #[case(GameConfig { board_size: 12, die_faces: 6, rolls_per_turn: 2, target_score: 100, players: 2 }, 5280)]
+    fn test_part_1_with(#[case] config: GameConfig, #[case] expected_score: u32) {
+        let input_str = read_file("src/example_input");
+        assert_eq!(day_21_part_1_with(&input_str, &config), expected_score);
+    }
+
+    #[rstest]
+    #[case(GameConfig::quantum(), 444356092776315)]
+    #[case(GameConfig { board_size: 7, die_faces: 2, rolls_per_turn: 2, target_score: 15, players: 2 }, 20501)]
+    fn test_part_2_with(#[case] config: GameConfig, #[case] expected_universes: u128) {
+        let input_str = read_file("src/example_input");
+        assert_eq!(day_21_part_2_with(&input_str, &config), expected_universes);
+    }
+
+    #[rstest]
+    #[case("src/example_input", vec![444356092776315, 341960390180808])]
+    #[case("src/input", vec![190897246590017, 90710140491134])]
+    fn test_quantum_solver(#[case] input_file: &str, #[case] expected_wins: Vec<u128>) {
+        let input_str = read_file(input_file);
+        let initial_state = GameState::from_str(&input_str).unwrap();
+        let mut solver = QuantumSolver::new(GameConfig::quantum());
+        let outcome = solver.solve(&initial_state);
+        assert_eq!(outcome.wins, expected_wins);
+        for (wins, turns) in outcome.wins.iter().zip(&outcome.turns) {
+            assert_eq!(*wins, turns.iter().sum::<u128>());
+        }
+        // Nobody can reach 21 in the first two turns
+        assert_eq!(outcome.turns[0][1..3], [0, 0]);
+
+        let probabilities = solver.win_probabilities(&initial_state);
+        assert!((probabilities.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+    }
+
+    #[rstest]
+    #[case(GameState::new(vec![4, 8], vec![20, 0], 0), 1.0)]
+    #[case(GameState::new(vec![4, 8], vec![0, 21], 0), 0.0)]
+    #[case(GameState::new(vec![4, 8], vec![0, 0], 0), 0.7760439136743381)]
+    #[case(GameState::new(vec![4, 8], vec![15, 15], 0), 0.9316518672815983)]
+    fn test_win_probabilities(#[case] state: GameState, #[case] expected_player1: f64) {
+        let probabilities = QuantumSolver::new(GameConfig::quantum()).win_probabilities(&state);
+        assert!((probabilities[0] - expected_player1).abs() < 1e-9);
+        assert!((probabilities[1] - (1.0 - expected_player1)).abs() < 1e-9);
+    }
+
+    #[rstest]
+    #[case("Player 1 starting position: 4\nPlayer 2 starting position: 8\nPlayer 3 starting position: 1\n", 1179900)]
+    #[case(
+        "Player 1 starting position: 4\nPlayer 2 starting position: 8\n",
+        739785
+    )]
+    fn test_part_1_players(#[case] input_str: &str, #[case] expected_score: u32) {
+        assert_eq!(day_21_part_1(input_str), expected_score);
+    }
+
+    #[test]
+    fn test_quantum_three_players() {
+        let input_str =
+            "Player 1 starting position: 4\nPlayer 2 starting position: 8\nPlayer 3 starting position: 1\n";
+        let config = GameConfig {
+            players: 3,
+            target_score: 12,
+            ..GameConfig::quantum()
+        };
+        let expected = vec![213354233613, 143254394492, 289704608426];
+        assert_eq!(day_21_part_2_with(input_str, &config), expected[2]);
+
+        let initial_state = GameState::from_str(input_str).unwrap();
+        assert_eq!(
+            QuantumSolver::new(config).solve(&initial_state).wins,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_replay_dice() {
+        let rolls = (1..=100).cycle().take(993).join(",");
+        let mut die = ReplayDice::from_str(&rolls).unwrap();
+        let mut players = parse_players(&read_file("src/example_input"));
+        assert_eq!(
+            game(&mut die, &mut players, &GameConfig::deterministic()),
+            739785
+        );
+
+        let mut die = ReplayDice::from_file("src/example_rolls").unwrap();
+        assert_eq!(die.iter(3).collect::<Vec<u16>>(), vec![9, 10, 3]);
+        assert_eq!(die.get_count(), 9);
+        assert!(ReplayDice::from_str("1 2 x").is_err());
+        assert!(matches!(
+            ReplayDice::from_file("src/missing_rolls"),
+            Err(InvalidDice::Io(_))
+        ));
+        assert!(matches!(
+            ReplayDice::from_file("src/example_input"),
+            Err(InvalidDice::Roll(_))
+        ));
+    }
+
+    #[rstest]
+    #[case(vec![0, 0, 1], vec![0, 0, 1000])]
+    #[case(vec![1, 0, 3], vec![250, 0, 750])]
+    fn test_loaded_dice(#[case] weights: Vec<u64>, #[case] expected: Vec<usize>) {
+        let mut die = LoadedDice::new(&weights, 21).unwrap();
+        let mut counts = vec![0usize; weights.len()];
+        for _ in 0..1000 {
+            counts[die.throw().unwrap() as usize - 1] += 1;
+        }
+        for (count, expected) in counts.iter().zip(&expected) {
+            assert!(count.abs_diff(*expected) <= 50, "{:?}", counts);
+        }
+    }
+
+    #[rstest]
+    #[case(vec![])]
+    #[case(vec![0, 0])]
+    fn test_loaded_dice_no_weight(#[case] weights: Vec<u64>) {
+        assert!(matches!(
+            LoadedDice::new(&weights, 21),
+            Err(InvalidDice::NoWeight)
+        ));
+    }
+
+    #[test]
+    fn test_loaded_dice_weight_overflow() {
+        assert!(matches!(
+            LoadedDice::new(&[u64::MAX, 1], 21),
+            Err(InvalidDice::WeightOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_monte_carlo() {
+        let config = GameConfig::quantum();
+        let exact = QuantumSolver::new(config).win_probabilities(&GameState::new(
+            vec![4, 8],
+            vec![0, 0],
+            0,
+        ));
+        let mut die = SeededDice::new(config.die_faces, 21);
+        let estimate = monte_carlo(&mut die, &[4, 8], &config, 20_000);
+        for (estimate, exact) in estimate.iter().zip(&exact) {
+            assert!((estimate - exact).abs() < 0.02, "{} vs {}", estimate, exact);
+        }
+
+        let mut die = ReplayDice::from_file("src/example_rolls").unwrap();
+        assert_eq!(monte_carlo(&mut die, &[4, 8], &config, 10), vec![0.0, 0.0]);
+    }
+
     #[rstest]
     #[case("src/example_input", 745 * 993)]
     #[case("src/input", 908091)]
@@ -303,7 +786,7 @@ mod tests {
     #[rstest]
     #[case("src/example_input", 444356092776315)]
     #[case("src/input", 190897246590017)]
-    fn test_part_2(#[case] input_file: &str, #[case] expected_score: u64) {
+    fn test_part_2(#[case] input_file: &str, #[case] expected_score: u128) {
         let input_str = read_file(input_file);
         let initial_state = GameState::from_str(&input_str).unwrap();
         assert_eq!(game_iteration_part_2(initial_state), expected_score);
@@ -1,8 +1,10 @@
+use std::collections::HashMap;
+use std::fmt::Display;
 use std::str::FromStr;
 use std::string::ParseError;
 
 use std::fs::File;
-use std::io::{BufReader, Read};
+use std::io::{self, BufReader, Read, Write};
 pub fn read_file(file: &str) -> String {
     let input = File::open(file).unwrap();
 
@@ -12,46 +14,410 @@ pub fn read_file(file: &str) -> String {
     return contents;
 }
 
+pub const INIT_REGION: Cuboid = Cuboid {
+    x: (-50, 50),
+    y: (-50, 50),
+    z: (-50, 50),
+};
+
 pub fn aoc_22_comp(input_str: &str) -> (u64, u64) {
     let reactor = Reactor::from_str(&input_str).unwrap();
-    let region = ((-50, 50), (-50, 50), (-50, 50));
-    let part_1 = reactor.count_region(region);
-
-    let region = reactor.commands.iter().map(|c| (c.x, c.y, c.z)).fold(
-        ((-50, 50), (-50, 50), (-50, 50)),
-        |current, c| {
-            (
-                (current.0 .0.min(c.0 .0), current.0 .1.max(c.0 .1)),
-                (current.1 .0.min(c.1 .0), current.1 .1.max(c.1 .1)),
-                (current.2 .0.min(c.2 .0), current.2 .1.max(c.2 .1)),
-            )
-        },
-    );
-    let part_2 = reactor.count_region(region);
+    let part_1 = reactor.count_region(&INIT_REGION);
+    let part_2 = match reactor.bounding_box() {
+        Some(region) => reactor.count_region(&region),
+        None => 0,
+    };
     return (part_1, part_2);
 }
 
-struct Reactor {
+/// Axis aligned box of cubes, all bounds are inclusive.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct Cuboid {
+    pub x: (i32, i32),
+    pub y: (i32, i32),
+    pub z: (i32, i32),
+}
+
+impl Cuboid {
+    pub fn new(x: (i32, i32), y: (i32, i32), z: (i32, i32)) -> Cuboid {
+        return Cuboid { x, y, z };
+    }
+
+    /// Each side fits in a `u64`, the product only overflows for cuboids that span most of
+    /// the `i32` range in every direction, see `volume_i128`.
+    pub fn volume(&self) -> u64 {
+        let side = |(lo, hi): (i32, i32)| (hi as i64 - lo as i64 + 1) as u64;
+        return side(self.x) * side(self.y) * side(self.z);
+    }
+
+    /// Volume that can not overflow, even for cuboids spanning the full `i32` range.
+    pub fn volume_i128(&self) -> i128 {
+        return (self.x.1 as i128 - self.x.0 as i128 + 1)
+            * (self.y.1 as i128 - self.y.0 as i128 + 1)
+            * (self.z.1 as i128 - self.z.0 as i128 + 1);
+    }
+
+    pub fn contains(&self, loc: (i32, i32, i32)) -> bool {
+        return self.x.0 <= loc.0
+            && loc.0 <= self.x.1
+            && self.y.0 <= loc.1
+            && loc.1 <= self.y.1
+            && self.z.0 <= loc.2
+            && loc.2 <= self.z.1;
+    }
+
+    pub fn contains_cuboid(&self, other: &Cuboid) -> bool {
+        return self.contains((other.x.0, other.y.0, other.z.0))
+            && self.contains((other.x.1, other.y.1, other.z.1));
+    }
+
+    pub fn intersection(&self, other: &Cuboid) -> Option<Cuboid> {
+        let x = (self.x.0.max(other.x.0), self.x.1.min(other.x.1));
+        let y = (self.y.0.max(other.y.0), self.y.1.min(other.y.1));
+        let z = (self.z.0.max(other.z.0), self.z.1.min(other.z.1));
+        if x.0 > x.1 || y.0 > y.1 || z.0 > z.1 {
+            return None;
+        }
+        return Some(Cuboid { x, y, z });
+    }
+
+    /// Smallest cuboid containing both cuboids.
+    pub fn bounding_box(&self, other: &Cuboid) -> Cuboid {
+        return Cuboid {
+            x: (self.x.0.min(other.x.0), self.x.1.max(other.x.1)),
+            y: (self.y.0.min(other.y.0), self.y.1.max(other.y.1)),
+            z: (self.z.0.min(other.z.0), self.z.1.max(other.z.1)),
+        };
+    }
+
+    /// The part of `self` outside of `other`, as at most six disjoint cuboids.
+    /// Slabs are cut off along x first, then y, then z.
+    pub fn subtract(&self, other: &Cuboid) -> Vec<Cuboid> {
+        let overlap = match self.intersection(other) {
+            Some(overlap) => overlap,
+            None => return vec![*self],
+        };
+        let mut pieces = vec![];
+        let mut rest = *self;
+        if rest.x.0 < overlap.x.0 {
+            pieces.push(Cuboid::new((rest.x.0, overlap.x.0 - 1), rest.y, rest.z));
+        }
+        if overlap.x.1 < rest.x.1 {
+            pieces.push(Cuboid::new((overlap.x.1 + 1, rest.x.1), rest.y, rest.z));
+        }
+        rest.x = overlap.x;
+        if rest.y.0 < overlap.y.0 {
+            pieces.push(Cuboid::new(rest.x, (rest.y.0, overlap.y.0 - 1), rest.z));
+        }
+        if overlap.y.1 < rest.y.1 {
+            pieces.push(Cuboid::new(rest.x, (overlap.y.1 + 1, rest.y.1), rest.z));
+        }
+        rest.y = overlap.y;
+        if rest.z.0 < overlap.z.0 {
+            pieces.push(Cuboid::new(rest.x, rest.y, (rest.z.0, overlap.z.0 - 1)));
+        }
+        if overlap.z.1 < rest.z.1 {
+            pieces.push(Cuboid::new(rest.x, rest.y, (overlap.z.1 + 1, rest.z.1)));
+        }
+        return pieces;
+    }
+
+    /// Both cuboids as disjoint cuboids: `self` followed by the part of `other` outside of it.
+    pub fn union(&self, other: &Cuboid) -> Vec<Cuboid> {
+        let mut pieces = vec![*self];
+        pieces.extend(other.subtract(self));
+        return pieces;
+    }
+}
+
+impl Display for Cuboid {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "x={}..{},y={}..{},z={}..{}",
+            self.x.0, self.x.1, self.y.0, self.y.1, self.z.0, self.z.1
+        )
+    }
+}
+
+impl FromStr for Cuboid {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, <Self as std::str::FromStr>::Err> {
+        let (x_min, s) = s.strip_prefix("x=").unwrap().split_once("..").unwrap();
+        let (x_max, s) = s.split_once(",").unwrap();
+        let (y_min, s) = s.strip_prefix("y=").unwrap().split_once("..").unwrap();
+        let (y_max, s) = s.split_once(",").unwrap();
+        let (z_min, z_max) = s.strip_prefix("z=").unwrap().split_once("..").unwrap();
+
+        return Ok(Cuboid {
+            x: (x_min.parse().unwrap(), x_max.parse().unwrap()),
+            y: (y_min.parse().unwrap(), y_max.parse().unwrap()),
+            z: (z_min.parse().unwrap(), z_max.parse().unwrap()),
+        });
+    }
+}
+
+/// A set of cubes stored as disjoint cuboids.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct CuboidSet {
+    cuboids: Vec<Cuboid>,
+}
+
+impl CuboidSet {
+    pub fn new() -> CuboidSet {
+        return CuboidSet::default();
+    }
+
+    pub fn cuboids(&self) -> &[Cuboid] {
+        return &self.cuboids;
+    }
+
+    pub fn insert(&mut self, cuboid: &Cuboid) {
+        self.remove(cuboid);
+        self.cuboids.push(*cuboid);
+    }
+
+    pub fn remove(&mut self, cuboid: &Cuboid) {
+        self.cuboids = self
+            .cuboids
+            .iter()
+            .flat_map(|current| current.subtract(cuboid))
+            .collect();
+    }
+
+    pub fn volume(&self) -> u64 {
+        return self.cuboids.iter().map(|cuboid| cuboid.volume()).sum();
+    }
+
+    pub fn count_region(&self, region: &Cuboid) -> u64 {
+        return self
+            .cuboids
+            .iter()
+            .filter_map(|cuboid| cuboid.intersection(region))
+            .map(|overlap| overlap.volume())
+            .sum();
+    }
+
+    pub fn contains(&self, loc: (i32, i32, i32)) -> bool {
+        return self.cuboids.iter().any(|cuboid| cuboid.contains(loc));
+    }
+}
+
+impl Display for CuboidSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for cuboid in &self.cuboids {
+            writeln!(f, "{}", cuboid)?;
+        }
+        return Ok(());
+    }
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MeshFormat {
+    /// Wavefront OBJ
+    Obj,
+    /// ASCII Stanford PLY
+    Ply,
+}
+
+// Corners of a unit box, indexed as x + 2y + 4z, and its faces wound counter clockwise
+// when seen from outside.
+const BOX_CORNERS: [(usize, usize, usize); 8] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (0, 1, 0),
+    (1, 1, 0),
+    (0, 0, 1),
+    (1, 0, 1),
+    (0, 1, 1),
+    (1, 1, 1),
+];
+const BOX_FACES: [[usize; 4]; 6] = [
+    [0, 2, 3, 1],
+    [4, 5, 7, 6],
+    [0, 1, 5, 4],
+    [2, 6, 7, 3],
+    [0, 4, 6, 2],
+    [1, 3, 7, 5],
+];
+
+impl CuboidSet {
+    /// The part of the set inside `region`.
+    pub fn clip(&self, region: &Cuboid) -> CuboidSet {
+        return CuboidSet {
+            cuboids: self
+                .cuboids
+                .iter()
+                .filter_map(|cuboid| cuboid.intersection(region))
+                .collect(),
+        };
+    }
+
+    /// Writes every cuboid as a closed box. Cube `(x, y, z)` spans `x..x+1` etc.,
+    /// so adjacent cuboids share their faces.
+    pub fn write_mesh(&self, writer: &mut impl Write, format: MeshFormat) -> io::Result<()> {
+        let vertex_count = self.cuboids.len() * BOX_CORNERS.len();
+        let face_count = self.cuboids.len() * BOX_FACES.len();
+        match format {
+            MeshFormat::Obj => writeln!(writer, "# {} cuboids", self.cuboids.len())?,
+            MeshFormat::Ply => {
+                writeln!(writer, "ply")?;
+                writeln!(writer, "format ascii 1.0")?;
+                writeln!(writer, "element vertex {}", vertex_count)?;
+                writeln!(writer, "property int x")?;
+                writeln!(writer, "property int y")?;
+                writeln!(writer, "property int z")?;
+                writeln!(writer, "element face {}", face_count)?;
+                writeln!(writer, "property list uchar int vertex_indices")?;
+                writeln!(writer, "end_header")?;
+            }
+        }
+
+        for cuboid in &self.cuboids {
+            let xs = [cuboid.x.0 as i64, cuboid.x.1 as i64 + 1];
+            let ys = [cuboid.y.0 as i64, cuboid.y.1 as i64 + 1];
+            let zs = [cuboid.z.0 as i64, cuboid.z.1 as i64 + 1];
+            for (i, j, k) in BOX_CORNERS {
+                match format {
+                    MeshFormat::Obj => writeln!(writer, "v {} {} {}", xs[i], ys[j], zs[k])?,
+                    MeshFormat::Ply => writeln!(writer, "{} {} {}", xs[i], ys[j], zs[k])?,
+                }
+            }
+        }
+
+        for n in 0..self.cuboids.len() {
+            let offset = n * BOX_CORNERS.len();
+            for face in BOX_FACES {
+                let [a, b, c, d] = face.map(|corner| corner + offset);
+                match format {
+                    // OBJ indices start at 1
+                    MeshFormat::Obj => {
+                        writeln!(writer, "f {} {} {} {}", a + 1, b + 1, c + 1, d + 1)?
+                    }
+                    MeshFormat::Ply => writeln!(writer, "4 {} {} {} {}", a, b, c, d)?,
+                }
+            }
+        }
+        return Ok(());
+    }
+}
+
+pub struct Reactor {
     commands: Vec<ReactorCommand>,
 }
 
 impl Reactor {
-    fn count_region(&self, region: ((i32, i32), (i32, i32), (i32, i32))) -> u64 {
-        let mut count = 0;
-        for i in region.0 .0..=region.0 .1 {
-            for j in region.1 .0..=region.1 .1 {
-                for k in region.2 .0..=region.2 .1 {
-                    count += self.check_loc((i, j, k)) as u64;
+    /// Counts the cubes that are on within `region` using inclusion-exclusion:
+    /// every command adds its own cuboid (if on) and cancels its overlap with all
+    /// cuboids counted before it, so only whole cuboids are ever visited.
+    pub fn count_region(&self, region: &Cuboid) -> u64 {
+        let mut count: i64 = 0;
+        for (cuboid, sign) in self.signed_cuboids() {
+            if let Some(overlap) = cuboid.intersection(region) {
+                count += sign * overlap.volume() as i64;
+            }
+        }
+        return count as u64;
+    }
+
+    fn signed_cuboids(&self) -> HashMap<Cuboid, i64> {
+        let mut cuboids: HashMap<Cuboid, i64> = HashMap::new();
+        for command in &self.commands {
+            let mut updates: HashMap<Cuboid, i64> = HashMap::new();
+            for (cuboid, sign) in &cuboids {
+                if let Some(overlap) = cuboid.intersection(&command.cuboid) {
+                    *updates.entry(overlap).or_insert(0) -= sign;
                 }
             }
+            if command.on {
+                *updates.entry(command.cuboid).or_insert(0) += 1;
+            }
+            for (cuboid, sign) in updates {
+                *cuboids.entry(cuboid).or_insert(0) += sign;
+            }
+            cuboids.retain(|_, sign| *sign != 0);
+        }
+        return cuboids;
+    }
+
+    /// The cubes that are on after all commands, as disjoint cuboids.
+    pub fn state(&self) -> CuboidSet {
+        return self.state_after(self.commands.len());
+    }
+
+    /// The cubes that are on after the first `step` commands.
+    pub fn state_after(&self, step: usize) -> CuboidSet {
+        let mut replay = self.replay();
+        while replay.step() < step && replay.advance() {}
+        return replay.state().clone();
+    }
+
+    pub fn replay(&self) -> Replay<'_> {
+        return Replay {
+            commands: &self.commands,
+            step: 0,
+            state: CuboidSet::new(),
+        };
+    }
+
+    /// Number of cubes that are on after each command.
+    pub fn on_counts(&self) -> Vec<u64> {
+        let mut replay = self.replay();
+        let mut counts = vec![];
+        while replay.advance() {
+            counts.push(replay.state().volume());
+        }
+        return counts;
+    }
+
+    /// Indices of the commands whose whole cuboid is set again by later commands,
+    /// so removing them would not change the final state.
+    pub fn overridden_commands(&self) -> Vec<usize> {
+        let mut later = CuboidSet::new();
+        let mut overridden = vec![];
+        for (i, command) in self.commands.iter().enumerate().rev() {
+            if later.count_region(&command.cuboid) == command.cuboid.volume() {
+                overridden.push(i);
+            } else {
+                later.insert(&command.cuboid);
+            }
+        }
+        overridden.reverse();
+        return overridden;
+    }
+
+    /// Writes the cubes that are on after all commands to `file` as a mesh,
+    /// limited to `region` if given.
+    pub fn export_mesh(
+        &self,
+        file: &str,
+        format: MeshFormat,
+        region: Option<&Cuboid>,
+    ) -> io::Result<()> {
+        let mut state = self.state();
+        if let Some(region) = region {
+            state = state.clip(region);
         }
-        return count;
+        let mut writer = io::BufWriter::new(File::create(file)?);
+        state.write_mesh(&mut writer, format)?;
+        return writer.flush();
     }
 
+    /// Smallest cuboid containing every command, or None if there are no commands.
+    pub fn bounding_box(&self) -> Option<Cuboid> {
+        return self
+            .commands
+            .iter()
+            .map(|command| command.cuboid)
+            .reduce(|current, cuboid| current.bounding_box(&cuboid));
+    }
+
+    #[cfg(test)]
     fn check_loc(&self, loc: (i32, i32, i32)) -> u8 {
         let mut on = false;
         for command in &self.commands {
-            if command.contains(loc) {
+            if command.cuboid.contains(loc) {
                 on = command.on;
             }
         }
@@ -59,6 +425,47 @@ impl Reactor {
     }
 }
 
+/// Applies the commands of a `Reactor` one at a time, so the state can be queried in between.
+pub struct Replay<'a> {
+    commands: &'a [ReactorCommand],
+    step: usize,
+    state: CuboidSet,
+}
+
+impl<'a> Replay<'a> {
+    /// Applies the next command, returns false if all commands were already applied.
+    pub fn advance(&mut self) -> bool {
+        let command = match self.commands.get(self.step) {
+            Some(command) => command,
+            None => return false,
+        };
+        if command.on {
+            self.state.insert(&command.cuboid);
+        } else {
+            self.state.remove(&command.cuboid);
+        }
+        self.step += 1;
+        return true;
+    }
+
+    /// Number of commands applied so far.
+    pub fn step(&self) -> usize {
+        return self.step;
+    }
+
+    pub fn state(&self) -> &CuboidSet {
+        return &self.state;
+    }
+
+    pub fn is_on(&self, loc: (i32, i32, i32)) -> bool {
+        return self.state.contains(loc);
+    }
+
+    pub fn count_region(&self, region: &Cuboid) -> u64 {
+        return self.state.count_region(region);
+    }
+}
+
 impl FromStr for Reactor {
     type Err = ParseError;
     fn from_str(s: &str) -> std::result::Result<Self, <Self as std::str::FromStr>::Err> {
@@ -74,20 +481,7 @@ impl FromStr for Reactor {
 #[derive(Debug, PartialEq)]
 struct ReactorCommand {
     on: bool,
-    x: (i32, i32),
-    y: (i32, i32),
-    z: (i32, i32),
-}
-
-impl ReactorCommand {
-    fn contains(&self, index: (i32, i32, i32)) -> bool {
-        return self.x.0 <= index.0
-            && index.0 <= self.x.1
-            && self.y.0 <= index.1
-            && index.1 <= self.y.1
-            && self.z.0 <= index.2
-            && index.2 <= self.z.1;
-    }
+    cuboid: Cuboid,
 }
 
 impl FromStr for ReactorCommand {
@@ -104,17 +498,9 @@ impl FromStr for ReactorCommand {
             on = false;
         }
 
-        let (x_min, s) = s.strip_prefix("x=").unwrap().split_once("..").unwrap();
-        let (x_max, s) = s.split_once(",").unwrap();
-        let (y_min, s) = s.strip_prefix("y=").unwrap().split_once("..").unwrap();
-        let (y_max, s) = s.split_once(",").unwrap();
-        let (z_min, z_max) = s.strip_prefix("z=").unwrap().split_once("..").unwrap();
-
         return Ok(ReactorCommand {
             on: on,
-            x: (x_min.parse().unwrap(), x_max.parse().unwrap()),
-            y: (y_min.parse().unwrap(), y_max.parse().unwrap()),
-            z: (z_min.parse().unwrap(), z_max.parse().unwrap()),
+            cuboid: Cuboid::from_str(s)?,
         });
     }
 }
@@ -125,55 +511,198 @@ mod tests {
     use rstest::rstest;
 
     #[rstest]
-    #[case("on x=-20..26,y=-36..17,z=-47..7",       ReactorCommand {on: true,x:(-20,26, ),  y:(-36,17),    z:(-47,7)})]
-    #[case("on x=-20..33,y=-21..23,z=-26..28",      ReactorCommand {on: true,x:(-20,33, ),  y:(-21,23, ),   z:(-26,28)})]
-    #[case("on x=-22..28,y=-29..23,z=-38..16",      ReactorCommand {on: true,x:(-22,28, ),  y:(-29,23, ),   z:(-38,16)})]
-    #[case("on x=-46..7,y=-6..46,z=-50..-1",        ReactorCommand {on: true,x:(-46,7,  ),  y:(-6,46,  ),   z:(-50,-1)})]
-    #[case("on x=-49..1,y=-3..46,z=-24..28",        ReactorCommand {on: true,x:(-49,1,  ),  y:(-3,46,  ),   z:(-24,28)})]
-    #[case("on x=2..47,y=-22..22,z=-23..27",        ReactorCommand {on: true,x:(2,47,   ),  y:(-22,22, ),   z:(-23,27)})]
-    #[case("on x=-27..23,y=-28..26,z=-21..29",      ReactorCommand {on: true,x:(-27,23, ),  y:(-28,26, ),   z:(-21,29)})]
-    #[case("on x=-39..5,y=-6..47,z=-3..44",         ReactorCommand {on: true,x:(-39,5,  ),  y:(-6,47,  ),   z:(-3,44)})]
-    #[case("on x=-30..21,y=-8..43,z=-13..34",       ReactorCommand {on: true,x:(-30,21, ),  y:(-8,43,  ),   z:(-13,34)})]
-    #[case("on x=-22..26,y=-27..20,z=-29..19",      ReactorCommand {on: true,x:(-22,26, ),  y:(-27,20, ),   z:(-29,19)})]
-    #[case("off x=-48..-32,y=26..41,z=-47..-37",    ReactorCommand {on: false,x:(-48,-32,),  y:(26,41,  ),   z:(-47,-37)})]
-    #[case("on x=-12..35,y=6..50,z=-50..-2",        ReactorCommand {on: true,x:(-12,35, ),  y:(6,50,   ),   z:(-50,-2)})]
-    #[case("off x=-48..-32,y=-32..-16,z=-15..-5",   ReactorCommand {on: false,x:(-48,-32,),  y:(-32,-16,),   z:(-15,-5)})]
-    #[case("on x=-18..26,y=-33..15,z=-7..46",       ReactorCommand {on: true,x:(-18,26, ),  y:(-33,15, ),   z:(-7,46)})]
-    #[case("off x=-40..-22,y=-38..-28,z=23..41",    ReactorCommand {on: false,x:(-40,-22,),  y:(-38,-28,),   z:(23,41)})]
-    #[case("on x=-16..35,y=-41..10,z=-47..6",       ReactorCommand {on: true,x:(-16,35, ),  y:(-41,10, ),   z:(-47,6)})]
-    #[case("off x=-32..-23,y=11..30,z=-14..3",      ReactorCommand {on: false,x:(-32,-23,),  y:(11,30,  ),   z:(-14,3)})]
-    #[case("on x=-49..-5,y=-3..45,z=-29..18",       ReactorCommand {on: true,x:(-49,-5, ),  y:(-3,45,  ),   z:(-29,18)})]
-    #[case("off x=18..30,y=-20..-8,z=-3..13",       ReactorCommand {on: false,x:(18,30,  ),  y:(-20,-8, ),   z:(-3,13)})]
-    #[case("on x=-41..9,y=-7..43,z=-33..15",        ReactorCommand {on: true,x:(-41,9,  ),  y:(-7,43,  ),   z:(-33,15)})]
+    #[case("on x=-20..26,y=-36..17,z=-47..7",       ReactorCommand {on: true, cuboid: Cuboid::new((-20, 26), (-36,17), (-47, 7))})]
+    #[case("on x=-20..33,y=-21..23,z=-26..28",      ReactorCommand {on: true, cuboid: Cuboid::new((-20, 33), (-21,23), (-26, 28))})]
+    #[case("on x=-22..28,y=-29..23,z=-38..16",      ReactorCommand {on: true, cuboid: Cuboid::new((-22, 28), (-29,23), (-38, 16))})]
+    #[case("on x=-46..7,y=-6..46,z=-50..-1",        ReactorCommand {on: true, cuboid: Cuboid::new((-46, 7), (-6,46), (-50, -1))})]
+    #[case("on x=-49..1,y=-3..46,z=-24..28",        ReactorCommand {on: true, cuboid: Cuboid::new((-49, 1), (-3,46), (-24, 28))})]
+    #[case("on x=2..47,y=-22..22,z=-23..27",        ReactorCommand {on: true, cuboid: Cuboid::new((2, 47), (-22,22), (-23, 27))})]
+    #[case("on x=-27..23,y=-28..26,z=-21..29",      ReactorCommand {on: true, cuboid: Cuboid::new((-27, 23), (-28,26), (-21, 29))})]
+    #[case("on x=-39..5,y=-6..47,z=-3..44",         ReactorCommand {on: true, cuboid: Cuboid::new((-39, 5), (-6,47), (-3, 44))})]
+    #[case("on x=-30..21,y=-8..43,z=-13..34",       ReactorCommand {on: true, cuboid: Cuboid::new((-30, 21), (-8,43), (-13, 34))})]
+    #[case("on x=-22..26,y=-27..20,z=-29..19",      ReactorCommand {on: true, cuboid: Cuboid::new((-22, 26), (-27,20), (-29, 19))})]
+    #[case("off x=-48..-32,y=26..41,z=-47..-37",    ReactorCommand {on: false, cuboid: Cuboid::new((-48, -32), (26,41), (-47, -37))})]
+    #[case("on x=-12..35,y=6..50,z=-50..-2",        ReactorCommand {on: true, cuboid: Cuboid::new((-12, 35), (6,50), (-50, -2))})]
+    #[case("off x=-48..-32,y=-32..-16,z=-15..-5",   ReactorCommand {on: false, cuboid: Cuboid::new((-48, -32), (-32,-16), (-15, -5))})]
+    #[case("on x=-18..26,y=-33..15,z=-7..46",       ReactorCommand {on: true, cuboid: Cuboid::new((-18, 26), (-33,15), (-7, 46))})]
+    #[case("off x=-40..-22,y=-38..-28,z=23..41",    ReactorCommand {on: false, cuboid: Cuboid::new((-40, -22), (-38,-28), (23, 41))})]
+    #[case("on x=-16..35,y=-41..10,z=-47..6",       ReactorCommand {on: true, cuboid: Cuboid::new((-16, 35), (-41,10), (-47, 6))})]
+    #[case("off x=-32..-23,y=11..30,z=-14..3",      ReactorCommand {on: false, cuboid: Cuboid::new((-32, -23), (11,30), (-14, 3))})]
+    #[case("on x=-49..-5,y=-3..45,z=-29..18",       ReactorCommand {on: true, cuboid: Cuboid::new((-49, -5), (-3,45), (-29, 18))})]
+    #[case("off x=18..30,y=-20..-8,z=-3..13",       ReactorCommand {on: false, cuboid: Cuboid::new((18, 30), (-20,-8), (-3, 13))})]
+    #[case("on x=-41..9,y=-7..43,z=-33..15",        ReactorCommand {on: true, cuboid: Cuboid::new((-41, 9), (-7,43), (-33, 15))})]
     fn test_parse_command(#[case] command_str: &str, #[case] command: ReactorCommand) {
         assert_eq!(ReactorCommand::from_str(command_str), Ok(command));
     }
 
     #[rstest]
-    #[case("src/example_input", 474140, Some(((-50, 50), (-50, 50), (-50, 50))))]
-    #[case("src/input", 642125, Some(((-50, 50), (-50, 50), (-50, 50))))]
+    #[case("src/example_input", 474140, Some(INIT_REGION))]
+    #[case("src/input", 642125, Some(INIT_REGION))]
     #[case("src/example_input", 2758514936282235, None)]
-    #[case("src/input", 642125, None)]
+    #[case("src/input", 1235164413198198, None)]
     fn test_reactor(
         #[case] input_file: &str,
         #[case] expected_count: u64,
-        #[case] mut region: Option<((i32, i32), (i32, i32), (i32, i32))>,
+        #[case] region: Option<Cuboid>,
     ) {
         let input_str = read_file(input_file);
         let reactor = Reactor::from_str(&input_str).unwrap();
-        if region.is_none() {
-            region = Some(reactor.commands.iter().map(|c| (c.x, c.y, c.z)).fold(
-                ((-50, 50), (-50, 50), (-50, 50)),
-                |current, c| {
-                    (
-                        (current.0 .0.min(c.0 .0), current.0 .1.max(c.0 .1)),
-                        (current.1 .0.min(c.1 .0), current.1 .1.max(c.1 .1)),
-                        (current.2 .0.min(c.2 .0), current.2 .1.max(c.2 .1)),
-                    )
-                },
-            ));
-        }
-
-        assert_eq!(reactor.count_region(region.unwrap()), expected_count)
+        let region = region.unwrap_or_else(|| reactor.bounding_box().unwrap());
+
+        assert_eq!(reactor.count_region(&region), expected_count);
+        assert_eq!(reactor.state().count_region(&region), expected_count);
+    }
+
+    #[test]
+    fn test_count_region_matches_brute_force() {
+        let reactor = Reactor::from_str(&read_file("src/example_input")).unwrap();
+        let region = Cuboid::new((-20, 10), (-5, 25), (-30, 0));
+        let state = reactor.state();
+        let mut expected = 0;
+        for x in region.x.0..=region.x.1 {
+            for y in region.y.0..=region.y.1 {
+                for z in region.z.0..=region.z.1 {
+                    expected += reactor.check_loc((x, y, z)) as u64;
+                    assert_eq!(state.contains((x, y, z)), reactor.check_loc((x, y, z)) == 1);
+                }
+            }
+        }
+        assert_eq!(reactor.count_region(&region), expected);
+    }
+
+    #[rstest]
+    #[case(Cuboid::new((0, 4), (0, 4), (0, 4)), Cuboid::new((1, 2), (1, 2), (1, 2)))]
+    #[case(Cuboid::new((0, 4), (0, 4), (0, 4)), Cuboid::new((3, 9), (-2, 2), (4, 4)))]
+    #[case(Cuboid::new((0, 4), (0, 4), (0, 4)), Cuboid::new((-1, 5), (-1, 5), (-1, 5)))]
+    #[case(Cuboid::new((0, 4), (0, 4), (0, 4)), Cuboid::new((5, 6), (0, 4), (0, 4)))]
+    fn test_cuboid_set_operations(#[case] a: Cuboid, #[case] b: Cuboid) {
+        let overlap = a.intersection(&b).map_or(0, |overlap| overlap.volume());
+        let difference = a.subtract(&b);
+        assert_eq!(
+            difference.iter().map(|c| c.volume()).sum::<u64>(),
+            a.volume() - overlap
+        );
+        for (i, piece) in difference.iter().enumerate() {
+            assert!(a.contains_cuboid(piece));
+            assert_eq!(piece.intersection(&b), None);
+            for other in &difference[i + 1..] {
+                assert_eq!(piece.intersection(other), None);
+            }
+        }
+
+        let union = a.union(&b);
+        assert_eq!(
+            union.iter().map(|c| c.volume()).sum::<u64>(),
+            a.volume() + b.volume() - overlap
+        );
+        assert!(union.iter().all(|c| a.bounding_box(&b).contains_cuboid(c)));
+    }
+
+    #[test]
+    fn test_cuboid_display() {
+        let input = "x=-20..26,y=-36..17,z=-47..7";
+        let cuboid = Cuboid::from_str(input).unwrap();
+        assert_eq!(cuboid.to_string(), input);
+        assert_eq!(cuboid.volume_i128(), cuboid.volume() as i128);
+        assert_eq!(
+            Cuboid::new((i32::MIN, i32::MAX), (0, 0), (0, 1)).volume_i128(),
+            1 << 33
+        );
+        assert_eq!(
+            Cuboid::new((i32::MIN, i32::MAX), (0, 0), (0, 1)).volume(),
+            1 << 33
+        );
+
+        let reactor =
+            Reactor::from_str("on x=0..2,y=0..2,z=0..2\noff x=1..1,y=1..1,z=1..1").unwrap();
+        let state = reactor.state();
+        assert_eq!(state.volume(), 26);
+        assert_eq!(state.to_string().lines().count(), state.cuboids().len());
+    }
+
+    #[test]
+    fn test_replay() {
+        let reactor = Reactor::from_str(
+            "on x=10..12,y=10..12,z=10..12
+on x=11..13,y=11..13,z=11..13
+off x=9..11,y=9..11,z=9..11
+on x=10..10,y=10..10,z=10..10",
+        )
+        .unwrap();
+        assert_eq!(reactor.on_counts(), vec![27, 46, 38, 39]);
+        assert_eq!(reactor.overridden_commands(), Vec::<usize>::new());
+
+        let mut replay = reactor.replay();
+        assert!(replay.advance() && replay.advance() && replay.advance());
+        assert_eq!(replay.step(), 3);
+        assert!(!replay.is_on((10, 10, 10)));
+        assert!(replay.is_on((12, 12, 12)));
+        assert_eq!(
+            replay.count_region(&Cuboid::new((13, 13), (11, 13), (11, 13))),
+            9
+        );
+        assert!(replay.advance());
+        assert!(replay.is_on((10, 10, 10)));
+        assert!(!replay.advance());
+
+        assert_eq!(reactor.state_after(1).volume(), 27);
+        assert_eq!(reactor.state_after(10), reactor.state());
+    }
+
+    #[test]
+    fn test_overridden_commands() {
+        let reactor = Reactor::from_str(
+            "on x=0..1,y=0..1,z=0..1
+on x=5..6,y=5..6,z=5..6
+off x=0..0,y=0..1,z=0..1
+on x=1..1,y=0..1,z=0..1
+off x=5..5,y=5..5,z=5..5",
+        )
+        .unwrap();
+        assert_eq!(reactor.overridden_commands(), vec![0]);
+        assert_eq!(reactor.on_counts(), vec![8, 16, 12, 12, 11]);
+    }
+
+    #[rstest]
+    #[case(MeshFormat::Obj)]
+    #[case(MeshFormat::Ply)]
+    fn test_write_mesh(#[case] format: MeshFormat) {
+        let reactor = Reactor::from_str(
+            "on x=0..2,y=0..2,z=0..2
+off x=1..1,y=1..1,z=1..1
+on x=60..61,y=0..0,z=0..0",
+        )
+        .unwrap();
+        let state = reactor.state().clip(&INIT_REGION);
+        assert_eq!(state.volume(), 26);
+
+        let mut mesh = vec![];
+        state.write_mesh(&mut mesh, format).unwrap();
+        let mesh = String::from_utf8(mesh).unwrap();
+        let cuboids = state.cuboids().len();
+        match format {
+            MeshFormat::Obj => {
+                assert_eq!(
+                    mesh.lines().filter(|l| l.starts_with("v ")).count(),
+                    8 * cuboids
+                );
+                assert_eq!(
+                    mesh.lines().filter(|l| l.starts_with("f ")).count(),
+                    6 * cuboids
+                );
+                assert!(mesh.contains("v 0 0 0\n"));
+                assert!(mesh.contains("f 1 3 4 2\n"));
+            }
+            MeshFormat::Ply => {
+                assert!(mesh.starts_with("ply\nformat ascii 1.0\n"));
+                assert!(mesh.contains(&format!("element vertex {}\n", 8 * cuboids)));
+                assert!(mesh.contains(&format!("element face {}\n", 6 * cuboids)));
+                let body = mesh.split_once("end_header\n").unwrap().1;
+                assert_eq!(body.lines().count(), 14 * cuboids);
+                assert!(body.contains("\n4 0 2 3 1\n"));
+            }
+        }
+        // The cube at x=60 is outside the init region, so no vertex may go past x=51
+        assert!(!mesh.contains(" 61 ") && !mesh.contains(" 62 "));
     }
 }